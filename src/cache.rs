@@ -6,13 +6,13 @@ use crate::data_structures::{WikipediaArticle, WikidataEntity};
 const WIKIPEDIA_CACHE_DIR: &str = "wikipedia_extractor/cache/wikipedia";
 const WIKIDATA_CACHE_DIR: &str = "wikipedia_extractor/cache/wikidata";
 
-fn get_wikipedia_cache_path(title: &str) -> PathBuf {
-    let filename = format!("{}.json", sanitize_filename(title));
+fn get_wikipedia_cache_path(key: &str) -> PathBuf {
+    let filename = format!("{}.json", sanitize_filename(key));
     Path::new(WIKIPEDIA_CACHE_DIR).join(filename)
 }
 
-fn get_wikidata_cache_path(id: &str) -> PathBuf {
-    let filename = format!("{}.json", sanitize_filename(id));
+fn get_wikidata_cache_path(key: &str) -> PathBuf {
+    let filename = format!("{}.json", sanitize_filename(key));
     Path::new(WIKIDATA_CACHE_DIR).join(filename)
 }
 
@@ -28,8 +28,8 @@ fn sanitize_filename(name: &str) -> String {
         .replace('|', "_")
 }
 
-pub fn save_article_to_cache(article: &WikipediaArticle) -> Result<(), Box<dyn std::error::Error>> {
-    let path = get_wikipedia_cache_path(&article.title);
+pub fn save_article_to_cache(key: &str, article: &WikipediaArticle) -> Result<(), Box<dyn std::error::Error>> {
+    let path = get_wikipedia_cache_path(key);
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?; // Create parent directories if they don't exist
     }
@@ -38,8 +38,8 @@ pub fn save_article_to_cache(article: &WikipediaArticle) -> Result<(), Box<dyn s
     Ok(())
 }
 
-pub fn load_article_from_cache(title: &str) -> Result<Option<WikipediaArticle>, Box<dyn std::error::Error>> {
-    let path = get_wikipedia_cache_path(title);
+pub fn load_article_from_cache(key: &str) -> Result<Option<WikipediaArticle>, Box<dyn std::error::Error>> {
+    let path = get_wikipedia_cache_path(key);
     match fs::read_to_string(&path) {
         Ok(json) => {
             let article: WikipediaArticle = serde_json::from_str(&json)?;
@@ -50,8 +50,8 @@ pub fn load_article_from_cache(title: &str) -> Result<Option<WikipediaArticle>,
     }
 }
 
-pub fn save_entity_to_cache(entity: &WikidataEntity) -> Result<(), Box<dyn std::error::Error>> {
-    let path = get_wikidata_cache_path(&entity.id);
+pub fn save_entity_to_cache(key: &str, entity: &WikidataEntity) -> Result<(), Box<dyn std::error::Error>> {
+    let path = get_wikidata_cache_path(key);
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?; // Create parent directories if they don't exist
     }
@@ -60,8 +60,8 @@ pub fn save_entity_to_cache(entity: &WikidataEntity) -> Result<(), Box<dyn std::
     Ok(())
 }
 
-pub fn load_entity_from_cache(id: &str) -> Result<Option<WikidataEntity>, Box<dyn std::error::Error>> {
-    let path = get_wikidata_cache_path(id);
+pub fn load_entity_from_cache(key: &str) -> Result<Option<WikidataEntity>, Box<dyn std::error::Error>> {
+    let path = get_wikidata_cache_path(key);
     match fs::read_to_string(&path) {
         Ok(json) => {
             let entity: WikidataEntity = serde_json::from_str(&json)?;
@@ -0,0 +1,150 @@
+use anyhow::Result;
+use git2::{ObjectType, Repository, TreeWalkMode, TreeWalkResult};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::history_scanner::CRQ_REGEX;
+use crate::scan_cache::ScanCache;
+
+/// A change request document, as written in `docs/CRQ-<n>-*.md`:
+///
+/// ```text
+/// **CRQ: CRQ-059: Title**
+///
+/// **Problem/Goal:**
+/// ...
+/// **Proposed Solution:**
+/// ...
+/// **Justification/Impact:**
+/// ...
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CrqDocument {
+    pub id: String,
+    pub title: String,
+    pub path: String,
+    pub problem: String,
+    pub proposed_solution: String,
+    pub justification: String,
+    /// Any other bold `**Heading:**` sections, keyed by heading.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub other_sections: BTreeMap<String, String>,
+}
+
+/// A commit that mentions a CRQ, with the files it mentions it in.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CrqCommit {
+    pub author: String,
+    pub timestamp: i64,
+    pub files: BTreeSet<String>,
+}
+
+/// One node of the CRQ → commits → files graph. `document` is `None` for CRQ ids that are
+/// mentioned but have no document; `commits` is empty for documents nothing refers to yet.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CrqTrace {
+    pub document: Option<CrqDocument>,
+    pub commits: BTreeMap<String, CrqCommit>,
+}
+
+/// Parses a CRQ document. Returns `None` if it lacks the `**CRQ: CRQ-<n>: Title**` line.
+pub fn parse_crq_document(path: &str, content: &str) -> Option<CrqDocument> {
+    let mut title_line = None;
+    let mut sections: Vec<(String, String)> = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if title_line.is_none() && trimmed.starts_with("**CRQ") {
+            title_line = Some(trimmed.trim_matches('*').to_string());
+            continue;
+        }
+        // Section headings are whole bold lines ending in a colon; bold list items are not
+        if let Some((heading, rest)) = trimmed.strip_prefix("**").and_then(|s| s.split_once(":**")) {
+            if !heading.contains("**") {
+                sections.push((heading.trim().to_string(), rest.trim().to_string()));
+                continue;
+            }
+        }
+        if let Some((_, body)) = sections.last_mut() {
+            if !body.is_empty() {
+                body.push('\n');
+            }
+            body.push_str(line);
+        }
+    }
+
+    let title_line = title_line?;
+    let id = CRQ_REGEX.find(&title_line)?.as_str().to_string();
+    let title = title_line.split_once(&id)
+        .map(|(_, rest)| rest.trim_start_matches(':').trim().to_string())
+        .unwrap_or_default();
+
+    let mut document = CrqDocument {
+        id,
+        title,
+        path: path.to_string(),
+        problem: String::new(),
+        proposed_solution: String::new(),
+        justification: String::new(),
+        other_sections: BTreeMap::new(),
+    };
+    for (heading, body) in sections {
+        let body = body.trim().to_string();
+        match heading.to_lowercase().as_str() {
+            "problem/goal" | "problem" | "goal" => document.problem = body,
+            "proposed solution" | "solution" => document.proposed_solution = body,
+            "justification/impact" | "justification" | "impact" => document.justification = body,
+            _ => {
+                document.other_sections.insert(heading, body);
+            },
+        }
+    }
+    Some(document)
+}
+
+/// Parses every CRQ document under `docs_dir` in the HEAD tree of `repo`. `path_prefix` is
+/// prepended to document paths, e.g. `"<repository>:"` for manifest scans.
+pub fn load_crq_documents(repo: &Repository, docs_dir: &str, path_prefix: &str) -> Result<Vec<CrqDocument>> {
+    let tree = repo.head()?.peel_to_tree()?;
+    let docs_dir = docs_dir.trim_end_matches('/');
+    let Ok(entry) = tree.get_path(Path::new(docs_dir)) else {
+        return Ok(Vec::new());
+    };
+    let docs_tree = repo.find_tree(entry.id())?;
+
+    let mut documents = Vec::new();
+    docs_tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        let name = entry.name().unwrap_or_default();
+        if entry.kind() != Some(ObjectType::Blob) || !name.ends_with(".md") || !CRQ_REGEX.is_match(name) {
+            return TreeWalkResult::Ok;
+        }
+        if let Ok(blob) = repo.find_blob(entry.id()) {
+            let path = format!("{}{}/{}{}", path_prefix, docs_dir, dir, name);
+            if let Some(document) = parse_crq_document(&path, &String::from_utf8_lossy(blob.content())) {
+                documents.push(document);
+            }
+        }
+        TreeWalkResult::Ok
+    })?;
+    Ok(documents)
+}
+
+/// Links each document to the commits and files where the scanner found its CRQ id.
+pub fn build_crq_graph(documents: Vec<CrqDocument>, scan_cache: &ScanCache) -> BTreeMap<String, CrqTrace> {
+    let mut graph: BTreeMap<String, CrqTrace> = BTreeMap::new();
+    for document in documents {
+        graph.insert(document.id.clone(), CrqTrace { document: Some(document), commits: BTreeMap::new() });
+    }
+    for finding in scan_cache.crq_links.values() {
+        let trace = graph.entry(finding.value.clone())
+            .or_insert_with(|| CrqTrace { document: None, commits: BTreeMap::new() });
+        for occurrence in &finding.occurrences {
+            trace.commits.entry(occurrence.commit.clone())
+                .or_insert_with(|| CrqCommit { author: occurrence.author.clone(), timestamp: occurrence.timestamp, files: BTreeSet::new() })
+                .files
+                .insert(occurrence.latest_path());
+        }
+    }
+    graph
+}
@@ -1,9 +1,61 @@
 use serde::{Serialize, Deserialize};
 
+/// What a link on a Wikipedia page points at.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    /// A page in the main namespace of the same wiki.
+    Article,
+    Category,
+    /// A `File:`, `Image:` or `Media:` page.
+    File,
+    Template,
+    /// A page on another Wikimedia wiki, including other language editions.
+    Interwiki,
+    /// A `#fragment` on the same page, such as a footnote.
+    Anchor,
+    External,
+    /// An edit, history or other `/w/index.php?action=...` link.
+    Edit,
+    /// Pages in other namespaces, such as `Special:`, `Help:` or `Talk:`.
+    #[default]
+    Other,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct WikipediaLink {
     pub href: String,
     pub text: String,
+    #[serde(default)]
+    pub kind: LinkKind,
+    /// The linked page's title as MediaWiki displays it, e.g. `Rust (programming language)`.
+    /// `None` for anchors and external links.
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub fragment: Option<String>,
+    /// Whether the link is in the article body rather than in navigation: the sidebar, header,
+    /// footer or a navbox.
+    #[serde(default)]
+    pub in_body: bool,
+}
+
+/// A heading of an article and the text under it, up to the next heading of any level.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ArticleSection {
+    /// 2 for `<h2>`, 3 for `<h3>`, and so on.
+    pub level: u8,
+    pub heading: String,
+    /// The heading's `id`, usable as a `#fragment`.
+    pub anchor: String,
+    pub text: String,
+    pub subsections: Vec<ArticleSection>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct InfoboxField {
+    pub key: String,
+    pub value: String,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -13,6 +65,21 @@ pub struct WikipediaArticle {
     pub revision_id: Option<u64>,
     pub content: String,
     pub links: Vec<WikipediaLink>,
+    /// The paragraphs before the first heading.
+    #[serde(default)]
+    pub lead: String,
+    #[serde(default)]
+    pub sections: Vec<ArticleSection>,
+    #[serde(default)]
+    pub infobox: Vec<InfoboxField>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Notes such as "For other uses, see ..." shown above the lead or a section.
+    #[serde(default)]
+    pub hatnotes: Vec<String>,
+    /// The text of each footnote in the reference list, in order.
+    #[serde(default)]
+    pub references: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -27,3 +94,15 @@ pub struct WikidataEntity {
     pub label: String,
     pub facts: Vec<WikidataFact>,
 }
+
+/// One result of a Wikidata entity search, in ranked order.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct EntityCandidate {
+    pub id: String,
+    pub label: String,
+    pub description: Option<String>,
+    /// What the search matched: `label`, `alias` or `description`.
+    pub match_type: String,
+    /// The label or alias text that matched.
+    pub match_text: String,
+}
@@ -0,0 +1,115 @@
+use anyhow::{Result, Context};
+use git2::{Repository, Oid, Commit, ErrorCode, FileMode, Signature};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+
+use crate::scan_cache::{ScanCache, FindingKind};
+
+/// Reference whose commits hold `scan_cache.json`, one commit per scan.
+pub const STATE_REF: &str = "refs/wikidata/scan-cache";
+/// Notes reference holding the findings each commit added and removed; readable with
+/// `git notes --ref wikidata show <commit>`.
+pub const NOTES_REF: &str = "refs/notes/wikidata";
+
+const STATE_FILE: &str = "scan_cache.json";
+
+/// The note attached to a scanned commit.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct CommitNote {
+    pub added: Vec<NoteEntry>,
+    pub removed: Vec<NoteEntry>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct NoteEntry {
+    pub kind: FindingKind,
+    pub value: String,
+    pub path: String,
+    pub line: Option<u32>,
+}
+
+/// Loads the scan cache stored at `STATE_REF`, or `None` if the repository has none yet.
+pub fn load_scan_cache(repo: &Repository) -> Result<Option<ScanCache>> {
+    let Some(commit) = ref_commit(repo, STATE_REF)? else {
+        return Ok(None);
+    };
+    let entry = commit.tree()?.get_name(STATE_FILE)
+        .context(format!("{} has no {}", STATE_REF, STATE_FILE))?
+        .to_object(repo)?;
+    let blob = entry.as_blob().context(format!("{} in {} is not a file", STATE_FILE, STATE_REF))?;
+    let scan_cache = serde_json::from_slice(blob.content())
+        .context(format!("Failed to deserialize {} from {}", STATE_FILE, STATE_REF))?;
+    Ok(Some(scan_cache))
+}
+
+/// Commits `scan_cache` onto `STATE_REF`, on top of the previous state so it can be pushed and
+/// fetched like any branch.
+pub fn save_scan_cache(repo: &Repository, scan_cache: &ScanCache) -> Result<Oid> {
+    let content = serde_json::to_string_pretty(scan_cache).context("Failed to serialize scan cache")?;
+    let mut tree = repo.treebuilder(None)?;
+    tree.insert(STATE_FILE, repo.blob(content.as_bytes())?, FileMode::Blob.into())?;
+    commit_tree(repo, STATE_REF, tree.write()?, "Update scan cache")
+}
+
+/// Rewrites `NOTES_REF` so that every commit of the top-level repository with findings carries a
+/// `CommitNote` listing them. Notes for commits whose findings were dropped, e.g. after a
+/// rewrite, disappear. Returns the number of notes written.
+pub fn write_commit_notes(repo: &Repository, scan_cache: &ScanCache) -> Result<usize> {
+    let mut notes: BTreeMap<&str, CommitNote> = BTreeMap::new();
+    let all_findings = [
+        &scan_cache.crq_links, &scan_cache.urls, &scan_cache.terms, &scan_cache.doc_comments,
+        &scan_cache.line_comments, &scan_cache.string_literals, &scan_cache.item_names,
+    ];
+    for finding in all_findings.into_iter().flat_map(|findings| findings.values()) {
+        let entry = |path: &str, line| NoteEntry { kind: finding.kind, value: finding.value.clone(), path: path.to_string(), line };
+        for occurrence in finding.occurrences.iter().filter(|o| o.repository.is_none() && o.submodule.is_none()) {
+            notes.entry(&occurrence.commit).or_default().added.push(entry(&occurrence.path, occurrence.line));
+        }
+        for removal in finding.removals.iter().filter(|o| o.repository.is_none() && o.submodule.is_none()) {
+            notes.entry(&removal.commit).or_default().removed.push(entry(&removal.path, removal.line));
+        }
+    }
+
+    // A flat tree of `<commit id>` -> note blob is a valid notes tree, and lets all notes go in
+    // with one commit instead of one per `Repository::note` call
+    let mut tree = repo.treebuilder(None)?;
+    for (commit, note) in &notes {
+        let content = serde_json::to_string_pretty(note).context("Failed to serialize commit note")?;
+        tree.insert(*commit, repo.blob(content.as_bytes())?, FileMode::Blob.into())?;
+    }
+    commit_tree(repo, NOTES_REF, tree.write()?, "Update wikidata notes")?;
+    Ok(notes.len())
+}
+
+/// Reads the note `write_commit_notes` attached to `commit`, if any.
+pub fn read_commit_note(repo: &Repository, commit: Oid) -> Result<Option<CommitNote>> {
+    let note = match repo.find_note(Some(NOTES_REF), commit) {
+        Ok(note) => note,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let message = note.message().context(format!("Note on {} is not UTF-8", commit))?;
+    Ok(Some(serde_json::from_str(message).context(format!("Failed to deserialize note on {}", commit))?))
+}
+
+fn ref_commit<'r>(repo: &'r Repository, name: &str) -> Result<Option<Commit<'r>>> {
+    match repo.find_reference(name) {
+        Ok(reference) => Ok(Some(reference.peel_to_commit()?)),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Commits `tree` onto `reference` (creating it if needed) unless it matches the current tip.
+fn commit_tree(repo: &Repository, reference: &str, tree: Oid, message: &str) -> Result<Oid> {
+    let parent = ref_commit(repo, reference)?;
+    if let Some(parent) = parent.as_ref().filter(|parent| parent.tree_id() == tree) {
+        return Ok(parent.id());
+    }
+    // Bare mirrors and CI checkouts often have no user configured
+    let signature = repo.signature()
+        .or_else(|_| Signature::now("wikidata-tool", "wikidata-tool@localhost"))?;
+    let tree = repo.find_tree(tree)?;
+    let parents: Vec<&Commit> = parent.iter().collect();
+    Ok(repo.commit(Some(reference), &signature, &signature, message, &tree, &parents)?)
+}
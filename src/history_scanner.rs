@@ -0,0 +1,770 @@
+use anyhow::{anyhow, Result, Context};
+use git2::{Repository, Oid, BlameOptions, ObjectType, Delta, Diff, DiffDelta, DiffFile, DiffFindOptions, DiffFormat, DiffLineType, DiffOptions, FileMode, Sort, AttrCheckFlags, AttrValue, Commit, RevparseMode, Tree};
+use rayon::prelude::*;
+use regex::Regex;
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::path_filter::PathFilter;
+use crate::rust_extractor::{extract_rust_fragments, RustFragment, RustFragmentKind};
+use crate::scan_cache::{ScanCache, DiffMode, FindingKind, Occurrence};
+use crate::term_extractor::TermExtractor;
+use crate::url_normalizer::canonicalize_url;
+
+lazy_static! {
+    pub(crate) static ref CRQ_REGEX: Regex = Regex::new(r"CRQ-\d+").unwrap();
+    // Deliberately greedy; `canonicalize_url` trims punctuation and unbalanced brackets afterwards
+    pub(crate) static ref URL_REGEX: Regex = Regex::new(r#"https?://[^\s<>"`|\\^]+"#).unwrap();
+}
+
+/// Stands in for the commit id of occurrences found in staged changes.
+pub const STAGED: &str = "STAGED";
+/// Stands in for the commit id of occurrences found in unstaged changes and untracked files.
+pub const UNSTAGED: &str = "UNSTAGED";
+
+/// Settings shared by every repository and submodule scanned in one run.
+pub struct ScanOptions {
+    pub path_filter: PathFilter,
+    pub term_extractor: TermExtractor,
+    pub diff_mode: DiffMode,
+}
+
+/// Restricts an ad-hoc scan to commits in a time window and/or by a given author.
+#[derive(Debug, Clone, Default)]
+pub struct CommitSelection {
+    /// Earliest commit time, in seconds since the epoch.
+    pub since: Option<i64>,
+    /// Latest commit time, in seconds since the epoch.
+    pub until: Option<i64>,
+    /// Case-insensitive substring of the author's name or email.
+    pub author: Option<String>,
+}
+
+impl CommitSelection {
+    pub fn matches(&self, commit: &Commit) -> bool {
+        let time = commit.time().seconds();
+        if self.since.is_some_and(|since| time < since) || self.until.is_some_and(|until| time > until) {
+            return false;
+        }
+        let Some(author) = &self.author else {
+            return true;
+        };
+        let author = author.to_lowercase();
+        let signature = commit.author();
+        let matched = [signature.name(), signature.email()].into_iter()
+            .flatten()
+            .any(|field| field.to_lowercase().contains(&author));
+        matched
+    }
+}
+
+/// Parses `--since`/`--until` values: either seconds since the epoch or a `YYYY-MM-DD` date
+/// (UTC), taken as the start of the day, or its last second when `end_of_day` is set.
+pub fn parse_date(value: &str, end_of_day: bool) -> Result<i64> {
+    if let Ok(seconds) = value.parse::<i64>() {
+        return Ok(seconds);
+    }
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        anyhow::bail!("Invalid date '{}', expected YYYY-MM-DD or seconds since the epoch", value);
+    };
+    let (year, month, day): (i64, i64, i64) = (year.parse()?, month.parse()?, day.parse()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        anyhow::bail!("Invalid date '{}'", value);
+    }
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let start = days * 86400;
+    Ok(if end_of_day { start + 86399 } else { start })
+}
+
+/// Findings extracted from every line of one blob, keyed by 1-based line number.
+type BlobFindings = HashMap<u32, Vec<(FindingKind, String)>>;
+
+/// Blobs are memoized by OID, plus whether they were read as Rust source, since the same
+/// content under a `.rs` name is extracted differently.
+type BlobKey = (Oid, bool);
+
+/// Files renamed or copied within one commit, as (old path, new path) pairs.
+#[derive(Default)]
+struct Moves {
+    renames: Vec<(String, String)>,
+    copies: Vec<(String, String)>,
+}
+
+/// A file changed by a commit, reduced to what the extraction and recording stages need.
+/// Added lines are numbered in `blob`, removed lines in `old_blob` (the parent side).
+struct ChangedFile {
+    path: String,
+    blob: Oid,
+    added_lines: Vec<u32>,
+    old_path: String,
+    old_blob: Oid,
+    removed_lines: Vec<u32>,
+    /// The file is a copy of `old_path`, which still exists; its removed lines are source lines
+    /// the copy left out, so they come off the copy rather than the source.
+    copied: bool,
+}
+
+struct CommitChanges {
+    commit: String,
+    author: String,
+    timestamp: i64,
+    files: Vec<ChangedFile>,
+    /// Files renamed or copied relative to the first parent.
+    moves: Moves,
+}
+
+/// Scans git history into a `ScanCache`. Commits are diffed and blobs extracted in parallel;
+/// findings are then recorded serially in history order. Extraction results are memoized by
+/// blob for the lifetime of the scanner, so content shared between commits, branches or
+/// submodules is only read once.
+pub struct HistoryScanner {
+    options: ScanOptions,
+    blob_findings: HashMap<BlobKey, BlobFindings>,
+    repository: Option<String>,
+}
+
+impl HistoryScanner {
+    pub fn new(options: ScanOptions) -> Self {
+        HistoryScanner { options, blob_findings: HashMap::new(), repository: None }
+    }
+
+    /// Names the repository that following scans attribute their findings to, for merging
+    /// several repositories into one `ScanCache`. Memoized blobs are kept, so content shared
+    /// between forks and mirrors is still only extracted once.
+    pub fn set_repository(&mut self, repository: Option<String>) {
+        self.repository = repository;
+    }
+
+    /// Prefixes a path or reference name with the current repository's name, if any.
+    fn scoped(&self, name: &str) -> String {
+        match &self.repository {
+            Some(repository) => format!("{}:{}", repository, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Walks the history of `repo` reachable from `tips`, stopping at each tip's watermark, and
+    /// records findings from every added line. Commits shared between tips are visited only once.
+    /// On return `watermarks` holds the commit each tip was scanned up to.
+    pub fn scan_repository(&mut self, repo: &Repository, submodule: Option<&str>, tips: &[(String, Oid)], watermarks: &mut BTreeMap<String, String>, scan_cache: &mut ScanCache) -> Result<()> {
+        let hidden = reconcile_watermarks(repo, tips, watermarks, self.repository.as_deref(), submodule, scan_cache)?;
+
+        let mut revwalk = repo.revwalk()?;
+        // Oldest first, so first/last seen commits come out in history order
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        if self.options.diff_mode == DiffMode::FirstParent {
+            revwalk.simplify_first_parent()?;
+        }
+        for (_, tip) in tips {
+            revwalk.push(*tip)?;
+        }
+        for oid in hidden {
+            revwalk.hide(oid)?;
+        }
+        let commits = revwalk.collect::<Result<Vec<Oid>, _>>()?;
+        self.scan_commits(repo, submodule, &commits, scan_cache)?;
+
+        for (name, tip) in tips {
+            watermarks.insert(name.clone(), tip.to_string());
+        }
+        Ok(())
+    }
+
+    /// Scans the commits selected by `revspec` (`A..B`, `A...B` or a single revision; HEAD when
+    /// `None`) that also match `selection`. Watermarks are neither used nor updated, so this is
+    /// meant for ad-hoc questions answered from a fresh `ScanCache`.
+    pub fn scan_range(&mut self, repo: &Repository, revspec: Option<&str>, selection: &CommitSelection, scan_cache: &mut ScanCache) -> Result<()> {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        if self.options.diff_mode == DiffMode::FirstParent {
+            revwalk.simplify_first_parent()?;
+        }
+
+        let spec = repo.revparse(revspec.unwrap_or("HEAD"))
+            .context(format!("Invalid revspec: {}", revspec.unwrap_or("HEAD")))?;
+        match (spec.from(), spec.to()) {
+            (Some(from), Some(to)) => {
+                revwalk.push(to.peel_to_commit()?.id())?;
+                if spec.mode().contains(RevparseMode::MERGE_BASE) {
+                    // `A...B`: everything on either side since they diverged
+                    revwalk.push(from.peel_to_commit()?.id())?;
+                    revwalk.hide(repo.merge_base(from.id(), to.id())?)?;
+                } else {
+                    revwalk.hide(from.peel_to_commit()?.id())?;
+                }
+            },
+            (Some(single), None) | (None, Some(single)) => revwalk.push(single.peel_to_commit()?.id())?,
+            (None, None) => anyhow::bail!("Revspec selects no commits"),
+        }
+
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            let oid = oid?;
+            if selection.matches(&repo.find_commit(oid)?) {
+                commits.push(oid);
+            }
+        }
+        self.scan_commits(repo, None, &commits, scan_cache)
+    }
+
+    /// Diffs and extracts `commits` in parallel, then records their findings in the given order.
+    fn scan_commits(&mut self, repo: &Repository, submodule: Option<&str>, commits: &[Oid], scan_cache: &mut ScanCache) -> Result<()> {
+        // git2 repositories can't be shared between threads, so each worker opens its own
+        let git_dir = repo.path();
+        let options = &self.options;
+        let changes = commits.par_iter()
+            .map_init(|| Repository::open(git_dir), |worker_repo, oid| {
+                let worker_repo = worker_repo.as_ref().map_err(|e| anyhow!("Failed to open {}: {}", git_dir.display(), e))?;
+                diff_commit(worker_repo, *oid, submodule, options)
+            })
+            .collect::<Result<Vec<CommitChanges>>>()?;
+
+        let blob_keys = changes.iter()
+            .flat_map(|commit| &commit.files)
+            .flat_map(|file| {
+                let added = (!file.added_lines.is_empty()).then(|| (file.blob, is_rust_source(&file.path)));
+                let removed = (!file.removed_lines.is_empty()).then(|| (file.old_blob, is_rust_source(&file.old_path)));
+                added.into_iter().chain(removed)
+            });
+        self.extract_blobs(git_dir, blob_keys)?;
+
+        let full_path = |path: &str| match submodule {
+            Some(submodule) => self.scoped(&format!("{}/{}", submodule, path)),
+            None => self.scoped(path),
+        };
+        for commit in changes {
+            let occurrence = |path: &str, line: Option<u32>| Occurrence {
+                commit: commit.commit.clone(),
+                path: path.to_string(),
+                line,
+                author: commit.author.clone(),
+                timestamp: commit.timestamp,
+                repository: self.repository.clone(),
+                submodule: submodule.map(str::to_string),
+                diff_mode: self.options.diff_mode,
+                current_path: None,
+                copied_from: None,
+            };
+            // Copies are taken from the source as it was before this commit's renames
+            for (old_path, new_path) in &commit.moves.copies {
+                scan_cache.record_copy(&full_path(old_path), occurrence(new_path, None));
+            }
+            for (old_path, new_path) in &commit.moves.renames {
+                scan_cache.record_rename(&full_path(old_path), &full_path(new_path));
+            }
+            // Additions go first, so a line that is merely edited never looks removed
+            for file in commit.files.iter().filter(|file| !file.added_lines.is_empty()) {
+                let findings = &self.blob_findings[&(file.blob, is_rust_source(&file.path))];
+                for line in &file.added_lines {
+                    for (kind, value) in findings.get(line).into_iter().flatten() {
+                        scan_cache.record(*kind, value, occurrence(&file.path, Some(*line)));
+                    }
+                }
+            }
+            for file in commit.files.iter().filter(|file| !file.removed_lines.is_empty()) {
+                let findings = &self.blob_findings[&(file.old_blob, is_rust_source(&file.old_path))];
+                let path = if file.copied { &file.path } else { &file.old_path };
+                for line in &file.removed_lines {
+                    for (kind, value) in findings.get(line).into_iter().flatten() {
+                        scan_cache.record_removal(*kind, value, occurrence(path, Some(*line)));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Scans every checked-out submodule of `repo`, and their submodules in turn.
+    /// `prefix` is the path of `repo` relative to the top-level repository.
+    pub fn scan_submodules(&mut self, repo: &Repository, prefix: &str, scan_cache: &mut ScanCache) -> Result<()> {
+        for submodule in repo.submodules()? {
+            let submodule_path = format!("{}{}", prefix, submodule.path().display());
+            let submodule_repo = match submodule.open() {
+                Ok(submodule_repo) => submodule_repo,
+                Err(_) => {
+                    println!("Skipping submodule {} (not checked out)", submodule_path);
+                    continue;
+                }
+            };
+
+            println!("Scanning submodule {}...", submodule_path);
+            let tips = resolve_tips(&submodule_repo, &[])?;
+            let watermark_key = self.scoped(&submodule_path);
+            let mut watermarks: BTreeMap<String, String> = scan_cache.submodule_watermarks.get(&watermark_key)
+                .map(|commit| ("HEAD".to_string(), commit.clone()))
+                .into_iter()
+                .collect();
+            self.scan_repository(&submodule_repo, Some(&submodule_path), &tips, &mut watermarks, scan_cache)
+                .context(format!("Failed to scan submodule: {}", submodule_path))?;
+            if let Some(commit) = watermarks.remove("HEAD") {
+                scan_cache.submodule_watermarks.insert(watermark_key, commit);
+            }
+
+            self.scan_submodules(&submodule_repo, &format!("{}/", submodule_path), scan_cache)?;
+        }
+        Ok(())
+    }
+
+    /// Records the lines added by staged changes (index vs HEAD) and by unstaged changes,
+    /// untracked files included (working tree vs index). Their occurrences carry `STAGED` or
+    /// `UNSTAGED` instead of a commit id. Nothing is committed, so no watermark moves; this is
+    /// meant for ad-hoc reports from a fresh `ScanCache`.
+    pub fn scan_uncommitted(&mut self, repo: &Repository, scan_cache: &mut ScanCache) -> Result<()> {
+        let workdir = repo.workdir().context("Repository has no working tree")?.to_path_buf();
+        // An unborn HEAD stages everything against the empty tree
+        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let index = repo.index()?;
+
+        let mut staged = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), Some(&mut diff_options(&self.options)))?;
+        let (mut staged_files, _) = diff_changes(repo, &mut staged, None, &self.options, false)?;
+        // Deleted files have a zero blob on the new side and nothing to extract
+        staged_files.retain(|file| !file.added_lines.is_empty());
+        self.extract_blobs(repo.path(), staged_files.iter().map(|file| (file.blob, is_rust_source(&file.path))))?;
+
+        let mut unstaged_options = diff_options(&self.options);
+        unstaged_options.include_untracked(true).recurse_untracked_dirs(true).show_untracked_content(true);
+        let mut unstaged = repo.diff_index_to_workdir(Some(&index), Some(&mut unstaged_options))?;
+        let (mut unstaged_files, _) = diff_changes(repo, &mut unstaged, None, &self.options, false)?;
+        unstaged_files.retain(|file| !file.added_lines.is_empty());
+        // Working tree content isn't in the object database, so it is read and hashed here
+        for file in &mut unstaged_files {
+            let content = std::fs::read(workdir.join(&file.path))
+                .context(format!("Failed to read {}", file.path))?;
+            file.blob = Oid::hash_object(ObjectType::Blob, &content)?;
+            let key = (file.blob, is_rust_source(&file.path));
+            if !self.blob_findings.contains_key(&key) {
+                let findings = extract_blob_findings(&content, key.1, &self.options.term_extractor);
+                self.blob_findings.insert(key, findings);
+            }
+        }
+
+        let author = repo.signature().ok()
+            .and_then(|signature| signature.name().map(str::to_string))
+            .unwrap_or_else(|| "Unknown".to_string());
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs() as i64;
+        for (label, files) in [(STAGED, staged_files), (UNSTAGED, unstaged_files)] {
+            for file in files {
+                let findings = &self.blob_findings[&(file.blob, is_rust_source(&file.path))];
+                for line in file.added_lines {
+                    let occurrence = Occurrence {
+                        commit: label.to_string(),
+                        path: file.path.clone(),
+                        line: Some(line),
+                        author: author.clone(),
+                        timestamp,
+                        repository: self.repository.clone(),
+                        submodule: None,
+                        diff_mode: self.options.diff_mode,
+                        current_path: None,
+                        copied_from: None,
+                    };
+                    for (kind, value) in findings.get(&line).into_iter().flatten() {
+                        scan_cache.record(*kind, value, occurrence.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs `git blame` at HEAD on every file of `repo` that still holds a live finding, and
+    /// attaches the commit and author each mention is credited to. Blame from an earlier pass over
+    /// the same repository is replaced. Submodules are not blamed.
+    pub fn blame_head(&mut self, repo: &Repository, scan_cache: &mut ScanCache) -> Result<()> {
+        let head = repo.head()?.peel_to_commit()?;
+        let head_id = head.id();
+        let tree = head.tree()?;
+        let mut files = Vec::new();
+        for path in scan_cache.live_paths(self.repository.as_deref()) {
+            // Files deleted since their last occurrence are no longer at HEAD
+            let Ok(entry) = tree.get_path(Path::new(&path)) else {
+                continue;
+            };
+            if entry.kind() == Some(ObjectType::Blob) {
+                let key = (entry.id(), is_rust_source(&path));
+                files.push((path, key));
+            }
+        }
+
+        let git_dir = repo.path();
+        self.extract_blobs(git_dir, files.iter().map(|(_, key)| *key))?;
+
+        let blob_findings = &self.blob_findings;
+        let blamed = files.par_iter()
+            .map_init(|| Repository::open(git_dir), |worker_repo, (path, key)| {
+                let worker_repo = worker_repo.as_ref().map_err(|e| anyhow!("Failed to open {}: {}", git_dir.display(), e))?;
+                let mut blame_options = BlameOptions::new();
+                blame_options.newest_commit(head_id);
+                let blame = worker_repo.blame_file(Path::new(path), Some(&mut blame_options))
+                    .context(format!("Failed to blame {}", path))?;
+                let lines = blob_findings[key].keys()
+                    .filter_map(|&line| {
+                        let hunk = blame.get_line(line as usize)?;
+                        let signature = hunk.final_signature();
+                        let author = signature.name().unwrap_or("Unknown").to_string();
+                        Some((line, hunk.final_commit_id().to_string(), author, signature.when().seconds()))
+                    })
+                    .collect::<Vec<_>>();
+                Ok((path, key, lines))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        scan_cache.clear_blame(self.repository.as_deref());
+        for (path, key, lines) in blamed {
+            for (line, commit, author, timestamp) in lines {
+                let occurrence = Occurrence {
+                    commit,
+                    path: path.clone(),
+                    line: Some(line),
+                    author,
+                    timestamp,
+                    repository: self.repository.clone(),
+                    submodule: None,
+                    diff_mode: self.options.diff_mode,
+                    current_path: None,
+                    copied_from: None,
+                };
+                for (kind, value) in &self.blob_findings[key][&line] {
+                    scan_cache.record_blame(*kind, value, occurrence.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Extracts, in parallel, every blob in `keys` that isn't memoized yet.
+    fn extract_blobs(&mut self, git_dir: &Path, keys: impl IntoIterator<Item = BlobKey>) -> Result<()> {
+        let pending: HashSet<BlobKey> = keys.into_iter()
+            .filter(|key| !self.blob_findings.contains_key(key))
+            .collect();
+
+        let term_extractor = &self.options.term_extractor;
+        let extracted = pending.into_par_iter()
+            .map_init(|| Repository::open(git_dir), |worker_repo, key| {
+                let worker_repo = worker_repo.as_ref().map_err(|e| anyhow!("Failed to open {}: {}", git_dir.display(), e))?;
+                let blob = worker_repo.find_blob(key.0)?;
+                Ok((key, extract_blob_findings(blob.content(), key.1, term_extractor)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.blob_findings.extend(extracted);
+        Ok(())
+    }
+}
+
+/// Lists the lines added by staged changes (index vs HEAD) as (path, line number, content), for
+/// files that `options` doesn't filter out.
+pub(crate) fn staged_added_lines(repo: &Repository, options: &ScanOptions) -> Result<Vec<(String, u32, String)>> {
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let index = repo.index()?;
+    let mut staged = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), Some(&mut diff_options(options)))?;
+    let (files, _) = diff_changes(repo, &mut staged, None, options, false)?;
+
+    let mut lines = Vec::new();
+    for file in files.iter().filter(|file| !file.added_lines.is_empty()) {
+        let blob = repo.find_blob(file.blob)?;
+        let content = String::from_utf8_lossy(blob.content());
+        let content_lines: Vec<&str> = content.lines().collect();
+        for &line in &file.added_lines {
+            if let Some(text) = content_lines.get(line as usize - 1) {
+                lines.push((file.path.clone(), line, text.to_string()));
+            }
+        }
+    }
+    Ok(lines)
+}
+
+/// Resolves the commits to scan from: HEAD plus every reference matching one of `ref_globs`.
+/// References that don't point (through tags) at a commit are skipped.
+pub fn resolve_tips(repo: &Repository, ref_globs: &[String]) -> Result<Vec<(String, Oid)>> {
+    let mut tips = vec![("HEAD".to_string(), repo.head()?.peel_to_commit()?.id())];
+    for glob in ref_globs {
+        for reference in repo.references_glob(glob)? {
+            let reference = reference?;
+            let (Some(name), Ok(commit)) = (reference.name(), reference.peel_to_commit()) else {
+                continue;
+            };
+            if !tips.iter().any(|(tip_name, _)| tip_name == name) {
+                tips.push((name.to_string(), commit.id()));
+            }
+        }
+    }
+    Ok(tips)
+}
+
+/// Checks that each tip's watermark is still one of its ancestors and returns the commits to
+/// hide from the walk. After a rebase, force-push or branch switch a watermark falls back to its
+/// merge base with the tip (or to a full rescan when the old commit is gone), and findings from
+/// abandoned or about-to-be-rescanned commits are dropped from the cache.
+fn reconcile_watermarks(repo: &Repository, tips: &[(String, Oid)], watermarks: &BTreeMap<String, String>, repository: Option<&str>, submodule: Option<&str>, scan_cache: &mut ScanCache) -> Result<Vec<Oid>> {
+    let mut hidden = Vec::new();
+    let mut rewritten = false;
+
+    for (name, tip) in tips {
+        let Some(last_oid) = watermarks.get(name).and_then(|s| Oid::from_str(s).ok()) else {
+            continue;
+        };
+
+        let last_exists = repo.find_commit(last_oid).is_ok();
+        if last_exists && (last_oid == *tip || repo.graph_descendant_of(*tip, last_oid)?) {
+            hidden.push(last_oid);
+            continue;
+        }
+
+        rewritten = true;
+        let merge_base = if last_exists { repo.merge_base(*tip, last_oid).ok() } else { None };
+        match merge_base {
+            Some(base) => {
+                println!("Last scanned commit {} of {} is no longer an ancestor, rescanning from merge base {}", last_oid, name, base);
+                hidden.push(base);
+            },
+            None => println!("Last scanned commit {} of {} is no longer reachable, rescanning its full history", last_oid, name),
+        }
+    }
+
+    if rewritten {
+        // Only findings from commits behind the remaining watermarks survive; everything else is
+        // either abandoned or will be recorded again by the rescan. Refs that aren't scanned this
+        // time keep their watermarks, so their history has to be kept too.
+        let mut kept = hidden.clone();
+        for (name, commit) in watermarks {
+            if tips.iter().any(|(tip_name, _)| tip_name == name) {
+                continue;
+            }
+            if let Some(oid) = Oid::from_str(commit).ok().filter(|oid| repo.find_commit(*oid).is_ok()) {
+                kept.push(oid);
+            }
+        }
+        let mut kept_commits = HashSet::new();
+        if !kept.is_empty() {
+            let mut revwalk = repo.revwalk()?;
+            for oid in &kept {
+                revwalk.push(*oid)?;
+            }
+            for oid in revwalk {
+                kept_commits.insert(oid?.to_string());
+            }
+        }
+        scan_cache.retain_occurrences(repository, submodule, |o| kept_commits.contains(&o.commit));
+    }
+
+    Ok(hidden)
+}
+
+/// Lists the lines `commit` adds and removes, per file that passes `should_skip_file`, according
+/// to `options.diff_mode`. Merge commits yield nothing under `DiffMode::SkipMerges`; under
+/// `DiffMode::Combined` they only yield lines that are new relative to every parent, i.e.
+/// content written while resolving the merge rather than content it brought in, and no removals,
+/// since those were already recorded on the branch that made them.
+fn diff_commit(repo: &Repository, oid: Oid, submodule: Option<&str>, options: &ScanOptions) -> Result<CommitChanges> {
+    let commit = repo.find_commit(oid)?;
+    let tree = commit.tree()?;
+
+    let (files, moves) = if commit.parent_count() == 0 {
+        // Initial commit, diff against empty tree
+        changed_lines(repo, None, &tree, submodule, options)?
+    } else if commit.parent_count() == 1 || options.diff_mode == DiffMode::FirstParent {
+        changed_lines(repo, Some(&commit.parent(0)?.tree()?), &tree, submodule, options)?
+    } else if options.diff_mode == DiffMode::SkipMerges {
+        (Vec::new(), Moves::default())
+    } else {
+        let (mut files, moves) = changed_lines(repo, Some(&commit.parent(0)?.tree()?), &tree, submodule, options)?;
+        for parent in commit.parents().skip(1) {
+            let (others, _) = changed_lines(repo, Some(&parent.tree()?), &tree, submodule, options)?;
+            files.retain_mut(|file| {
+                let Some(other) = others.iter().find(|other| other.path == file.path) else {
+                    return false;
+                };
+                file.added_lines.retain(|line| other.added_lines.contains(line));
+                file.removed_lines.clear();
+                !file.added_lines.is_empty()
+            });
+        }
+        (files, moves)
+    };
+
+    let author = commit.author().name().unwrap_or("Unknown").to_string();
+    Ok(CommitChanges {
+        commit: commit.id().to_string(),
+        author,
+        timestamp: commit.time().seconds(),
+        files,
+        moves,
+    })
+}
+
+/// Diffs `old_tree` (or the empty tree) against `new_tree` and collects the added and removed
+/// lines, along with the files that were renamed or copied. Renamed and copied files only
+/// contribute the lines that differ from their source, so moving or copying a file doesn't make
+/// its content look new; `ScanCache::record_rename` and `record_copy` account for the rest.
+fn changed_lines(repo: &Repository, old_tree: Option<&Tree>, new_tree: &Tree, submodule: Option<&str>, options: &ScanOptions) -> Result<(Vec<ChangedFile>, Moves)> {
+    let mut diff = repo.diff_tree_to_tree(old_tree, Some(new_tree), Some(&mut diff_options(options)))?;
+    diff_changes(repo, &mut diff, submodule, options, true)
+}
+
+fn diff_options(options: &ScanOptions) -> DiffOptions {
+    // Files over the size cap are reported as binary, so they are skipped by `diff_changes`
+    let mut diff_options = DiffOptions::new();
+    diff_options.max_size(options.path_filter.max_blob_size as i64);
+    diff_options
+}
+
+/// Collects the added and removed lines of an already computed diff; see `changed_lines`.
+/// Without `detect_copies` a copied file is reported as added in full, for callers that don't
+/// record copies.
+fn diff_changes(repo: &Repository, diff: &mut Diff, submodule: Option<&str>, options: &ScanOptions, detect_copies: bool) -> Result<(Vec<ChangedFile>, Moves)> {
+    diff.find_similar(Some(DiffFindOptions::new().renames(true).copies(detect_copies)))?;
+
+    let moved = |status: Delta| -> Vec<(String, String)> {
+        diff.deltas()
+            .filter(|delta| delta.status() == status)
+            .filter_map(|delta| {
+                let old_path = delta.old_file().path()?.display().to_string();
+                let new_path = delta.new_file().path()?.display().to_string();
+                Some((old_path, new_path))
+            })
+            .collect()
+    };
+    let moves = Moves { renames: moved(Delta::Renamed), copies: moved(Delta::Copied) };
+
+    let mut files: Vec<ChangedFile> = Vec::new();
+    let mut skipped_paths: HashMap<(String, bool), bool> = HashMap::new();
+    diff.print(DiffFormat::Patch, |delta, _hunk, line| {
+        let (side, line_number) = match line.origin_value() {
+            DiffLineType::Addition => (delta.new_file(), line.new_lineno()),
+            DiffLineType::Deletion => (delta.old_file(), line.old_lineno()),
+            _ => return true,
+        };
+        let Some(line_number) = line_number else {
+            return true;
+        };
+        let path = side.path().map(|p| p.display().to_string()).unwrap_or_default();
+        let is_addition = line.origin_value() == DiffLineType::Addition;
+        let skipped = *skipped_paths.entry((path.clone(), is_addition))
+            .or_insert_with(|| should_skip_file(repo, submodule, &delta, &side, &path, &options.path_filter));
+        if skipped {
+            return true;
+        }
+
+        let new_path = delta.new_file().path().map(|p| p.display().to_string()).unwrap_or_default();
+        let file = match files.last_mut() {
+            Some(file) if file.path == new_path => file,
+            _ => {
+                files.push(ChangedFile {
+                    path: new_path,
+                    blob: delta.new_file().id(),
+                    added_lines: Vec::new(),
+                    old_path: delta.old_file().path().map(|p| p.display().to_string()).unwrap_or_default(),
+                    old_blob: delta.old_file().id(),
+                    removed_lines: Vec::new(),
+                    copied: delta.status() == Delta::Copied,
+                });
+                files.last_mut().unwrap()
+            },
+        };
+        if is_addition {
+            file.added_lines.push(line_number);
+        } else {
+            file.removed_lines.push(line_number);
+        }
+        true
+    })?;
+    Ok((files, moves))
+}
+
+/// Whether one side of a changed file should be left out of the scan: submodule gitlinks,
+/// binary or oversized blobs, paths rejected by the include/exclude globs, and files marked
+/// `linguist-generated` or `linguist-vendored` in `.gitattributes`.
+fn should_skip_file(repo: &Repository, submodule: Option<&str>, delta: &DiffDelta, side: &DiffFile, path: &str, path_filter: &PathFilter) -> bool {
+    if side.mode() == FileMode::Commit {
+        return true;
+    }
+    if delta.flags().is_binary() || side.is_binary() || side.size() > path_filter.max_blob_size {
+        return true;
+    }
+
+    let full_path = match submodule {
+        Some(submodule) => format!("{}/{}", submodule, path),
+        None => path.to_string(),
+    };
+    if !path_filter.is_included(&full_path) {
+        return true;
+    }
+
+    ["linguist-generated", "linguist-vendored"].iter().any(|attr| {
+        let value = repo.get_attr(Path::new(path), attr, AttrCheckFlags::FILE_THEN_INDEX).ok().flatten();
+        AttrValue::from_string(value) == AttrValue::True
+    })
+}
+
+fn is_rust_source(path: &str) -> bool {
+    path.ends_with(".rs")
+}
+
+/// Runs the line extractors over every line of a blob. Rust sources that parse are also split
+/// into comments, literals and item names, grouped by the line they start on.
+fn extract_blob_findings(content: &[u8], rust_source: bool, term_extractor: &TermExtractor) -> BlobFindings {
+    let text = String::from_utf8_lossy(content);
+    let mut rust_fragments: Option<HashMap<u32, Vec<RustFragment>>> = None;
+    if rust_source {
+        if let Some(fragments) = extract_rust_fragments(&text) {
+            let mut by_line: HashMap<u32, Vec<RustFragment>> = HashMap::new();
+            for fragment in fragments {
+                by_line.entry(fragment.line).or_default().push(fragment);
+            }
+            rust_fragments = Some(by_line);
+        }
+    }
+
+    let mut findings = BlobFindings::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index as u32 + 1;
+        let line_fragments = rust_fragments.as_ref()
+            .map(|by_line| by_line.get(&line_number).map_or(&[][..], Vec::as_slice));
+        let line_findings = extract_line_findings(term_extractor, line, line_fragments);
+        if !line_findings.is_empty() {
+            findings.insert(line_number, line_findings);
+        }
+    }
+    findings
+}
+
+/// Runs the CRQ, URL and term extractors over a single line. For Rust sources that parsed,
+/// `rust_fragments` holds the comments, literals and item names starting on this line; they
+/// become findings of their own and terms come only from their prose.
+fn extract_line_findings(term_extractor: &TermExtractor, content: &str, rust_fragments: Option<&[RustFragment]>) -> Vec<(FindingKind, String)> {
+    let mut findings = Vec::new();
+    for m in CRQ_REGEX.find_iter(content) {
+        findings.push((FindingKind::CrqLink, m.as_str().to_string()));
+    }
+    for url in URL_REGEX.find_iter(content).filter_map(|m| canonicalize_url(m.as_str())) {
+        findings.push((FindingKind::Url, url));
+    }
+
+    let Some(rust_fragments) = rust_fragments else {
+        findings.extend(term_extractor.extract(content).into_iter().map(|term| (FindingKind::Term, term)));
+        return findings;
+    };
+    for fragment in rust_fragments {
+        let kind = match fragment.kind {
+            RustFragmentKind::DocComment => FindingKind::DocComment,
+            RustFragmentKind::LineComment => FindingKind::LineComment,
+            RustFragmentKind::StringLiteral => FindingKind::StringLiteral,
+            RustFragmentKind::ItemName => FindingKind::ItemName,
+        };
+        findings.push((kind, fragment.text.clone()));
+        if fragment.kind != RustFragmentKind::ItemName {
+            findings.extend(term_extractor.extract(&fragment.text).into_iter().map(|term| (FindingKind::Term, term)));
+        }
+    }
+    findings
+}
@@ -2,8 +2,28 @@ pub mod data_structures;
 pub mod wikipedia_parser;
 pub mod wikidata_client;
 pub mod cache;
+pub mod scan_cache;
+pub mod path_filter;
+pub mod term_extractor;
+pub mod rust_extractor;
+pub mod url_normalizer;
+pub mod history_scanner;
+pub mod git_store;
+pub mod policy;
+pub mod crq_document;
+pub mod resolver;
 
-pub use data_structures::{WikipediaArticle, WikidataFact, WikidataEntity};
-pub use wikipedia_parser::extract_article_data;
-pub use wikidata_client::fetch_wikidata_entity;
-pub use cache::{save_article_to_cache, load_article_from_cache, save_entity_to_cache, load_entity_from_cache};
\ No newline at end of file
+pub use data_structures::{WikipediaArticle, WikipediaLink, LinkKind, ArticleSection, InfoboxField, WikidataFact, WikidataEntity, EntityCandidate};
+pub use wikipedia_parser::{extract_article_data, extract_article_data_with, CleaningOptions};
+pub use wikidata_client::{fetch_wikidata_entity, search_wikidata_entities};
+pub use cache::{save_article_to_cache, load_article_from_cache, save_entity_to_cache, load_entity_from_cache};
+pub use scan_cache::{ScanCache, Finding, FindingKind, FindingStatus, Occurrence};
+pub use path_filter::{PathFilter, PathFilterConfig};
+pub use term_extractor::TermExtractor;
+pub use rust_extractor::{extract_rust_fragments, RustFragment, RustFragmentKind};
+pub use url_normalizer::{canonicalize_url, classify_url, UrlClass};
+pub use history_scanner::{HistoryScanner, ScanOptions, CommitSelection};
+pub use git_store::{CommitNote, NoteEntry};
+pub use policy::{PolicyConfig, PolicyViolation};
+pub use crq_document::{parse_crq_document, CrqDocument, CrqTrace};
+pub use resolver::{resolve_urls, resolve_terms};
@@ -1,111 +1,615 @@
 use anyhow::{Result, Context};
-use git2::{Repository, Oid, DiffFormat, DiffLineType};
+use git2::Repository;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::fs;
-use regex::Regex;
-use lazy_static::lazy_static;
-use clap::Parser;
-use serde::{Serialize, Deserialize};
+use clap::{Parser, Subcommand};
+use serde::Deserialize;
+use wikidata_tool::scan_cache::{ScanCache, DiffMode, Finding};
+use wikidata_tool::path_filter::{PathFilter, PathFilterConfig};
+use wikidata_tool::term_extractor::TermExtractor;
+use wikidata_tool::git_store;
+use wikidata_tool::resolver;
+use wikidata_tool::wikidata_client;
+use wikidata_tool::wikipedia_parser::CleaningOptions;
+use wikidata_tool::crq_document::{build_crq_graph, load_crq_documents, CrqTrace};
+use wikidata_tool::policy::{self, PolicyConfig};
+use wikidata_tool::url_normalizer::canonicalize_url;
+use wikidata_tool::history_scanner::{HistoryScanner, ScanOptions, CommitSelection, parse_date, resolve_tips};
 
-lazy_static! {
-    static ref CRQ_REGEX: Regex = Regex::new(r"CRQ-\d+").unwrap();
-    static ref URL_REGEX: Regex = Regex::new(r"https?://[\w./?=#&%~-]+[^\.,\s\n\r)]").unwrap();
-    static ref WORD_REGEX: Regex = Regex::new(r"\b[a-zA-Z_][a-zA-Z0-9_]*\b").unwrap();
+/// Reference globs scanned by `--all-refs`.
+const ALL_REF_GLOBS: &[&str] = &["refs/heads/*", "refs/remotes/*", "refs/tags/*"];
+
+/// Scanner settings read from the config file, if there is one.
+#[derive(Deserialize, Debug, Default)]
+struct ScanConfig {
+    #[serde(flatten)]
+    path_filter: PathFilterConfig,
+    /// Rules checked by the git hooks.
+    #[serde(default)]
+    policy: PolicyConfig,
+    /// What to strip from Wikipedia pages fetched by --resolve.
+    #[serde(default)]
+    cleaning: CleaningOptions,
+}
+
+/// Repositories scanned into one merged corpus by `--manifest`.
+#[derive(Deserialize, Debug)]
+struct Manifest {
+    repositories: Vec<ManifestEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ManifestEntry {
+    /// Name findings are attributed to; defaults to the directory name without a `.git` suffix.
+    name: Option<String>,
+    /// A local clone or bare mirror, relative to the manifest's directory unless absolute.
+    path: PathBuf,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Install or run the git hooks that enforce the CRQ and link policies
+    Hooks {
+        #[command(subcommand)]
+        action: HooksAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HooksAction {
+    /// Write pre-commit and commit-msg hooks into the repository
+    Install {
+        /// Replace existing hooks that weren't installed by this tool
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check the staged changes (run by the pre-commit hook)
+    PreCommit,
+    /// Check a commit message (run by the commit-msg hook)
+    CommitMsg {
+        message_file: PathBuf,
+    },
 }
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The path to the repository to scan (defaults to current directory)
     #[arg(long, default_value = ".")]
     repo_to_scan_path: PathBuf,
+
+    /// Keep scan state on refs/wikidata/scan-cache and per-commit findings in refs/notes/wikidata instead of .wikidata_cache
+    #[arg(long)]
+    store_in_git: bool,
+
+    /// Scan every repository listed in this JSON manifest into one merged corpus
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Where the merged corpus of a --manifest scan is kept (defaults to wikidata_corpus.json next to the manifest)
+    #[arg(long)]
+    corpus: Option<PathBuf>,
+
+    /// Also scan every local branch, remote branch and tag
+    #[arg(long)]
+    all_refs: bool,
+
+    /// Also scan every reference matching this glob (e.g. "refs/heads/feature/*"); may be repeated
+    #[arg(long = "refs", value_name = "GLOB")]
+    refs: Vec<String>,
+
+    /// Also scan the history of every submodule, recursively
+    #[arg(long)]
+    recurse_submodules: bool,
+
+    /// Scanner config file with include/exclude globs (defaults to .wikidata_tool.json in the repository)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Only scan paths matching this glob; may be repeated
+    #[arg(long = "include", value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip paths matching this glob; may be repeated
+    #[arg(long = "exclude", value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Skip blobs larger than this many bytes
+    #[arg(long)]
+    max_blob_size: Option<u64>,
+
+    /// Reduce extracted terms to their stems
+    #[arg(long)]
+    stem: bool,
+
+    /// Scan only the commits in this revspec (e.g. "v1.0..v1.1") without touching the scan cache
+    #[arg(long, value_name = "REVSPEC")]
+    range: Option<String>,
+
+    /// Only scan commits made on or after this date (YYYY-MM-DD or seconds since the epoch)
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Only scan commits made on or before this date (YYYY-MM-DD or seconds since the epoch)
+    #[arg(long)]
+    until: Option<String>,
+
+    /// Only scan commits whose author name or email contains this text
+    #[arg(long)]
+    author: Option<String>,
+
+    /// Scan only staged and unstaged changes, untracked files included, without touching the scan cache
+    #[arg(long)]
+    uncommitted: bool,
+
+    /// Write the results of a --range/--since/--until/--author/--uncommitted scan, or the --crq-graph, to this JSON file
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// How merge commits are attributed
+    #[arg(long, value_enum, default_value_t = DiffMode::Combined)]
+    diff_mode: DiffMode,
+
+    /// Number of worker threads for diffing and extraction (defaults to one per CPU)
+    #[arg(long)]
+    jobs: Option<usize>,
+
+    /// After scanning, fetch and cache the article and entity behind each new Wikipedia or Wikidata URL
+    #[arg(long)]
+    resolve: bool,
+
+    /// After scanning, search Wikidata for this many of the most mentioned unresolved terms
+    #[arg(long, value_name = "N")]
+    resolve_terms: Option<usize>,
+
+    /// Wikidata API endpoint used by --resolve-terms
+    #[arg(long, default_value = wikidata_client::WIKIDATA_API_URL)]
+    wikidata_api: String,
+
+    /// After scanning, run git blame at HEAD to credit each current finding to a commit and author
+    #[arg(long)]
+    blame: bool,
+
+    /// Instead of scanning, parse the CRQ documents and show the commits and files referencing each
+    #[arg(long)]
+    crq_graph: bool,
+
+    /// Instead of scanning, list the CRQ references and URLs that are no longer mentioned anywhere
+    #[arg(long)]
+    removed: bool,
+
+    /// Instead of scanning, print where the given URL, CRQ id or term is mentioned
+    #[arg(long)]
+    query: Option<String>,
+}
+
+fn print_query(scan_cache: &ScanCache, value: &str) {
+    let mut findings = scan_cache.query(value);
+    if findings.is_empty() {
+        if let Some(canonical) = canonicalize_url(value) {
+            findings = scan_cache.query(&canonical);
+        }
+    }
+    if findings.is_empty() {
+        println!("No mentions of '{}' found.", value);
+        return;
+    }
+    for finding in findings {
+        println!("{:?} '{}' (first seen {}, last seen {})", finding.kind, finding.value, finding.first_seen_commit, finding.last_seen_commit);
+        if let Some(url_class) = &finding.url_class {
+            println!("  classified as {:?}", url_class);
+        }
+        if let Some(wikidata_id) = &finding.wikidata_id {
+            println!("  resolved to Wikidata entity {}", wikidata_id);
+        }
+        for candidate in &finding.alternatives {
+            println!("    or {} '{}' ({} match)", candidate.id, candidate.label, candidate.match_type);
+        }
+        match &finding.removed_in_commit {
+            Some(commit) => println!("  removed in {}", commit),
+            None => println!("  live ({} mention(s))", finding.live_count),
+        }
+        for occurrence in &finding.occurrences {
+            let line = occurrence.line.map(|l| l.to_string()).unwrap_or_else(|| "?".to_string());
+            println!("  {}:{} in {} by {} at {} ({:?})", occurrence.full_path(), line, occurrence.commit, occurrence.author, occurrence.timestamp, occurrence.diff_mode);
+            if let Some(current_path) = &occurrence.current_path {
+                println!("    since renamed to {}", current_path);
+            }
+        }
+        for (path, count) in &finding.counts_by_file {
+            println!("  {} live mention(s) in {}", count, path);
+        }
+        for blamed in &finding.blame {
+            let line = blamed.line.map(|l| l.to_string()).unwrap_or_else(|| "?".to_string());
+            println!("  at HEAD in {}:{}, blamed on {} by {} at {}", blamed.full_path(), line, blamed.commit, blamed.author, blamed.timestamp);
+        }
+    }
+}
+
+/// Prints the CRQ references and URLs whose every mention has been deleted, with where and by
+/// whom the last one went.
+fn print_removed(scan_cache: &ScanCache) {
+    let removed = scan_cache.removed_links();
+    if removed.is_empty() {
+        println!("No CRQ references or URLs have been removed.");
+        return;
+    }
+    for finding in removed {
+        println!("{:?} '{}' (first seen {})", finding.kind, finding.value, finding.first_seen_commit);
+        if let Some(removal) = finding.removals.last() {
+            let line = removal.line.map(|l| l.to_string()).unwrap_or_else(|| "?".to_string());
+            println!("  last removed from {}:{} in {} by {} at {}", removal.full_path(), line, removal.commit, removal.author, removal.timestamp);
+        }
+    }
+}
+
+fn print_crq_graph(graph: &BTreeMap<String, CrqTrace>) {
+    for (id, trace) in graph {
+        match &trace.document {
+            Some(document) => println!("{}: {} ({})", id, document.title, document.path),
+            None => println!("{}: (no document)", id),
+        }
+        if trace.commits.is_empty() {
+            println!("  not referenced by any commit");
+        }
+        for (commit, crq_commit) in &trace.commits {
+            println!("  {} by {} at {}", commit, crq_commit.author, crq_commit.timestamp);
+            for file in &crq_commit.files {
+                println!("    {}", file);
+            }
+        }
+    }
+}
+
+/// Prints the CRQ references and URLs of an ad-hoc scan with their number of mentions, and
+/// with every place they're mentioned if `locations` is set.
+fn print_report(scan_cache: &ScanCache, locations: bool) {
+    let print_locations = |finding: &Finding| {
+        for occurrence in finding.occurrences.iter().filter(|_| locations) {
+            let line = occurrence.line.map(|l| l.to_string()).unwrap_or_else(|| "?".to_string());
+            println!("    {}:{} ({})", occurrence.full_path(), line, occurrence.commit);
+        }
+    };
+    println!("CRQ references ({}):", scan_cache.crq_links.len());
+    for finding in scan_cache.crq_links.values() {
+        println!("  {} ({} mention(s))", finding.value, finding.occurrences.len());
+        print_locations(finding);
+    }
+    println!("URLs ({}):", scan_cache.urls.len());
+    for finding in scan_cache.urls.values() {
+        let class = finding.url_class.as_ref().map(|c| format!(" [{:?}]", c)).unwrap_or_default();
+        println!("  {}{} ({} mention(s))", finding.value, class, finding.occurrences.len());
+        print_locations(finding);
+    }
+    println!("Distinct terms: {}", scan_cache.terms.len());
+}
+
+/// Reads a manifest and resolves each entry to a unique name and a path.
+fn load_manifest(manifest_path: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let manifest_content = fs::read_to_string(manifest_path)
+        .context(format!("Failed to read manifest: {}", manifest_path.display()))?;
+    let manifest: Manifest = serde_json::from_str(&manifest_content)
+        .context(format!("Failed to deserialize manifest: {}", manifest_path.display()))?;
+    let base_dir = manifest_path.parent().unwrap_or(Path::new("."));
+
+    let mut repositories: Vec<(String, PathBuf)> = Vec::new();
+    for entry in manifest.repositories {
+        let path = base_dir.join(&entry.path);
+        let name = match entry.name {
+            Some(name) => name,
+            None => path.file_name()
+                .map(|name| name.to_string_lossy().trim_end_matches(".git").to_string())
+                .context(format!("Can't derive a repository name from {}", path.display()))?,
+        };
+        if repositories.iter().any(|(existing, _)| *existing == name) {
+            anyhow::bail!("Repository name '{}' appears twice in the manifest; set distinct names", name);
+        }
+        repositories.push((name, path));
+    }
+    Ok(repositories)
+}
+
+/// The watermarks of one repository. A manifest scan keeps every repository's references in
+/// `ref_watermarks` under a `<name>:` prefix; a single repository keeps HEAD in `last_scanned_commit`.
+fn load_watermarks(scan_cache: &ScanCache, name: Option<&str>) -> BTreeMap<String, String> {
+    let Some(name) = name else {
+        let mut watermarks = scan_cache.ref_watermarks.clone();
+        if let Some(commit) = &scan_cache.last_scanned_commit {
+            watermarks.insert("HEAD".to_string(), commit.clone());
+        }
+        return watermarks;
+    };
+    let prefix = format!("{}:", name);
+    scan_cache.ref_watermarks.iter()
+        .filter_map(|(key, commit)| Some((key.strip_prefix(&prefix)?.to_string(), commit.clone())))
+        .collect()
+}
+
+fn store_watermarks(scan_cache: &mut ScanCache, name: Option<&str>, mut watermarks: BTreeMap<String, String>) {
+    let Some(name) = name else {
+        scan_cache.last_scanned_commit = watermarks.remove("HEAD");
+        scan_cache.ref_watermarks = watermarks;
+        return;
+    };
+    let prefix = format!("{}:", name);
+    scan_cache.ref_watermarks.retain(|key, _| !key.starts_with(&prefix));
+    for (reference, commit) in watermarks {
+        scan_cache.ref_watermarks.insert(format!("{}{}", prefix, reference), commit);
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
-struct ScanCache {
-    last_scanned_commit: Option<String>,
-    found_crq_links: Vec<String>,
-    found_urls: Vec<String>,
-    found_terms: Vec<String>,
+/// Reads the scanner config (`--config`, or `.wikidata_tool.json` in `base_dir`) and applies the
+/// command-line overrides.
+fn load_config(args: &Args, base_dir: &Path) -> Result<ScanConfig> {
+    let config_path = args.config.clone().unwrap_or_else(|| base_dir.join(".wikidata_tool.json"));
+    let mut config: ScanConfig = if config_path.exists() {
+        let config_content = fs::read_to_string(&config_path)
+            .context(format!("Failed to read config file: {}", config_path.display()))?;
+        serde_json::from_str(&config_content)
+            .context(format!("Failed to deserialize config file: {}", config_path.display()))?
+    } else if args.config.is_some() {
+        anyhow::bail!("Config file not found: {}", config_path.display());
+    } else {
+        ScanConfig::default()
+    };
+    config.path_filter.include.extend(args.include.iter().cloned());
+    config.path_filter.exclude.extend(args.exclude.iter().cloned());
+    if args.max_blob_size.is_some() {
+        config.path_filter.max_blob_size = args.max_blob_size;
+    }
+    Ok(config)
+}
+
+fn scan_options(args: &Args, config: &ScanConfig) -> Result<ScanOptions> {
+    Ok(ScanOptions {
+        path_filter: PathFilter::new(&config.path_filter).context("Invalid include/exclude glob")?,
+        term_extractor: TermExtractor::new(args.stem),
+        diff_mode: args.diff_mode,
+    })
+}
+
+/// Runs a `hooks` subcommand. The checks print every violation and fail if there was any, which
+/// makes git abort the commit.
+fn run_hooks(args: &Args, action: &HooksAction) -> Result<()> {
+    let repo = Repository::open(&args.repo_to_scan_path)
+        .context(format!("Failed to open repository at: {}", args.repo_to_scan_path.display()))?;
+    let base_dir = repo.workdir().unwrap_or(repo.path()).to_path_buf();
+    let config = load_config(args, &base_dir)?;
+
+    let violations = match action {
+        HooksAction::Install { force } => {
+            let executable = std::env::current_exe().context("Failed to locate the wikidata-tool executable")?;
+            for hook_path in policy::install_hooks(&repo, &executable, *force)? {
+                println!("Installed {}", hook_path.display());
+            }
+            return Ok(());
+        },
+        HooksAction::PreCommit => {
+            policy::use_hook_index(&repo)?;
+            policy::check_staged(&repo, &scan_options(args, &config)?, &config.policy)?
+        },
+        HooksAction::CommitMsg { message_file } => {
+            policy::use_hook_index(&repo)?;
+            let message = fs::read_to_string(message_file)
+                .context(format!("Failed to read commit message: {}", message_file.display()))?;
+            let known_crqs = policy::known_crqs(&repo, &config.policy.crq_docs_dir)?;
+            policy::check_commit_message(&message, &config.policy, &known_crqs)
+        },
+    };
+    for violation in &violations {
+        eprintln!("{}", violation);
+    }
+    if !violations.is_empty() {
+        anyhow::bail!("{} policy violation(s)", violations.len());
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    let repo_to_scan_path = args.repo_to_scan_path;
+    if let Some(Command::Hooks { action }) = &args.command {
+        return run_hooks(&args, action);
+    }
 
-    let repo = Repository::open(&repo_to_scan_path)
-        .context(format!("Failed to open repository at: {}", repo_to_scan_path.display()))?;
+    // Without a manifest there is one unnamed repository, cached in its own .wikidata_cache
+    let (repositories, base_dir, cache_file_path) = match &args.manifest {
+        Some(manifest_path) => {
+            let repositories = load_manifest(manifest_path)?
+                .into_iter()
+                .map(|(name, path)| (Some(name), path))
+                .collect::<Vec<_>>();
+            let base_dir = manifest_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+            let corpus_path = args.corpus.clone().unwrap_or_else(|| base_dir.join("wikidata_corpus.json"));
+            (repositories, base_dir, corpus_path)
+        },
+        None => {
+            let repo_to_scan_path = args.repo_to_scan_path.clone();
+            Repository::open(&repo_to_scan_path)
+                .context(format!("Failed to open repository at: {}", repo_to_scan_path.display()))?;
 
-    let cache_dir = repo_to_scan_path.join(".wikidata_cache"); // Use a hidden directory for cache
-    fs::create_dir_all(&cache_dir)
-        .context(format!("Failed to create cache directory: {}", cache_dir.display()))?;
+            let cache_dir = repo_to_scan_path.join(".wikidata_cache"); // Use a hidden directory for cache
+            if !args.store_in_git {
+                fs::create_dir_all(&cache_dir)
+                    .context(format!("Failed to create cache directory: {}", cache_dir.display()))?;
+            }
+            (vec![(None, repo_to_scan_path.clone())], repo_to_scan_path, cache_dir.join("scan_cache.json"))
+        },
+    };
+    if args.store_in_git && args.manifest.is_some() {
+        anyhow::bail!("--store-in-git can't be combined with --manifest; the merged corpus is kept in --corpus");
+    }
 
-    let cache_file_path = cache_dir.join("scan_cache.json");
-    let mut scan_cache: ScanCache = if cache_file_path.exists() {
+    // A repository switching to --store-in-git carries its existing file cache over once
+    let git_cache = if args.store_in_git {
+        let repo = Repository::open(&args.repo_to_scan_path)?;
+        git_store::load_scan_cache(&repo)?
+    } else {
+        None
+    };
+    let mut scan_cache: ScanCache = if let Some(git_cache) = git_cache {
+        git_cache
+    } else if cache_file_path.exists() {
         let cache_content = fs::read_to_string(&cache_file_path)
-            .context("Failed to read scan_cache.json")?;
+            .context(format!("Failed to read {}", cache_file_path.display()))?;
         serde_json::from_str(&cache_content)
-            .context("Failed to deserialize scan_cache.json")?
+            .context(format!("Failed to deserialize {}", cache_file_path.display()))?
     } else {
-        ScanCache::default()
+        ScanCache::new()
     };
 
-    let last_scanned_commit_oid: Option<Oid> = scan_cache.last_scanned_commit.as_ref()
-        .and_then(|s| Oid::from_str(s).ok());
+    if let Some(value) = &args.query {
+        print_query(&scan_cache, value);
+        return Ok(());
+    }
+    if args.removed {
+        print_removed(&scan_cache);
+        return Ok(());
+    }
 
-    let head_commit = repo.head()?.peel_to_commit()?;
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push(head_commit.id())?;
+    let config = load_config(&args, &base_dir)?;
+    if args.crq_graph {
+        let mut documents = Vec::new();
+        for (name, path) in &repositories {
+            let repo = Repository::open(path)
+                .context(format!("Failed to open repository at: {}", path.display()))?;
+            let prefix = name.as_ref().map(|name| format!("{}:", name)).unwrap_or_default();
+            documents.extend(load_crq_documents(&repo, &config.policy.crq_docs_dir, &prefix)?);
+        }
+        let graph = build_crq_graph(documents, &scan_cache);
+        print_crq_graph(&graph);
+        if let Some(output) = &args.output {
+            let content = serde_json::to_string_pretty(&graph)
+                .context("Failed to serialize CRQ graph")?;
+            fs::write(output, content)
+                .context(format!("Failed to write {}", output.display()))?;
+        }
+        return Ok(());
+    }
+    let options = scan_options(&args, &config)?;
 
-    if let Some(last_oid) = last_scanned_commit_oid {
-        revwalk.hide(last_oid)?;
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global()
+            .context("Failed to start worker pool")?;
     }
+    let mut scanner = HistoryScanner::new(options);
 
-    println!("Scanning for changes since last scan...");
+    let selection = CommitSelection {
+        since: args.since.as_deref().map(|since| parse_date(since, false)).transpose()?,
+        until: args.until.as_deref().map(|until| parse_date(until, true)).transpose()?,
+        author: args.author.clone(),
+    };
+    let has_selection = args.range.is_some() || args.since.is_some() || args.until.is_some() || args.author.is_some();
+    if has_selection || args.uncommitted {
+        // Ad-hoc scans start from an empty cache and leave scan_cache.json and its watermarks alone
+        let mut range_cache = ScanCache::new();
+        for (name, path) in &repositories {
+            let repo = Repository::open(path)
+                .context(format!("Failed to open repository at: {}", path.display()))?;
+            scanner.set_repository(name.clone());
+            if has_selection {
+                scanner.scan_range(&repo, args.range.as_deref(), &selection, &mut range_cache)?;
+            }
+            if args.uncommitted {
+                scanner.scan_uncommitted(&repo, &mut range_cache)
+                    .context(format!("Failed to scan uncommitted changes in: {}", path.display()))?;
+            }
+        }
+        print_report(&range_cache, args.uncommitted);
+        if let Some(output) = &args.output {
+            let content = serde_json::to_string_pretty(&range_cache)
+                .context("Failed to serialize scan results")?;
+            fs::write(output, content)
+                .context(format!("Failed to write {}", output.display()))?;
+        }
+        return Ok(());
+    }
 
-    let mut new_crq_links = Vec::new();
-    let mut new_urls = Vec::new();
-    let mut new_terms = Vec::new();
-
-    for oid in revwalk {
-        let oid = oid?;
-        let commit = repo.find_commit(oid)?;
-
-        // Compare with parent to get changes in this commit
-        let diff = if commit.parent_count() > 0 {
-            let parent = commit.parent(0)?;
-            repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&commit.tree()?), None)?
-        } else {
-            // Initial commit, diff against empty tree
-            repo.diff_tree_to_tree(None, Some(&commit.tree()?), None)?
-        };
+    if !scan_cache.is_current() {
+        println!("Scan cache format is outdated, rescanning full history...");
+        scan_cache = ScanCache::new();
+    }
 
-        match diff.print(DiffFormat::Patch, |_, _, _| {
-            Ok(true)
-        }) {
-            Ok(_) => {},
-            Err(e) => return Err(e.into()), // Convert git2::Error to anyhow::Error
-        };
+    println!("Scanning for changes since last scan...");
+
+    let mut ref_globs = args.refs.clone();
+    if args.all_refs {
+        ref_globs.extend(ALL_REF_GLOBS.iter().map(|glob| glob.to_string()));
     }
 
-    // Aggregate new findings into the cache
-    scan_cache.found_crq_links.extend(new_crq_links.into_iter().filter(|link| !scan_cache.found_crq_links.contains(link)));
-    scan_cache.found_urls.extend(new_urls.into_iter().filter(|url| !scan_cache.found_urls.contains(url)));
-    scan_cache.found_terms.extend(new_terms.into_iter().filter(|term| !scan_cache.found_terms.contains(term)));
+    for (name, path) in &repositories {
+        if let Some(name) = name {
+            println!("Scanning repository {} ({})...", name, path.display());
+        }
+        let repo = Repository::open(path)
+            .context(format!("Failed to open repository at: {}", path.display()))?;
+        scanner.set_repository(name.clone());
+        let tips = resolve_tips(&repo, &ref_globs)?;
+
+        let mut watermarks = load_watermarks(&scan_cache, name.as_deref());
+        scanner.scan_repository(&repo, None, &tips, &mut watermarks, &mut scan_cache)
+            .context(format!("Failed to scan repository at: {}", path.display()))?;
+        store_watermarks(&mut scan_cache, name.as_deref(), watermarks);
+
+        // Bare mirrors have no checked-out submodules to recurse into
+        if args.recurse_submodules && !repo.is_bare() {
+            scanner.scan_submodules(&repo, "", &mut scan_cache)?;
+        }
+        if args.blame {
+            scanner.blame_head(&repo, &mut scan_cache)
+                .context(format!("Failed to blame repository at: {}", path.display()))?;
+        }
+    }
 
-    // Update last scanned commit
-    scan_cache.last_scanned_commit = Some(head_commit.id().to_string());
+    if args.resolve || args.resolve_terms.is_some() {
+        let client = reqwest::Client::builder()
+            .user_agent(resolver::USER_AGENT)
+            .build()
+            .context("Failed to build HTTP client")?;
+        let runtime = tokio::runtime::Runtime::new().context("Failed to start async runtime")?;
+        if args.resolve {
+            println!("Resolving Wikipedia and Wikidata URLs...");
+            let resolved = runtime.block_on(resolver::resolve_urls(&client, &mut scan_cache, &config.cleaning));
+            println!("Resolved {} URL(s) to Wikidata entities", resolved);
+        }
+        if let Some(max_terms) = args.resolve_terms {
+            println!("Searching Wikidata for up to {} terms...", max_terms);
+            let resolved = runtime.block_on(resolver::resolve_terms(&client, &args.wikidata_api, &mut scan_cache, max_terms));
+            println!("Resolved {} term(s) to Wikidata entities", resolved);
+        }
+    }
 
-    // Write updated cache to file
-    let updated_cache_content = serde_json::to_string_pretty(&scan_cache)
-        .context("Failed to serialize scan_cache.json")?;
-    fs::write(&cache_file_path, updated_cache_content)
-        .context("Failed to write scan_cache.json")?;
+    if args.store_in_git {
+        let repo = Repository::open(&args.repo_to_scan_path)?;
+        git_store::save_scan_cache(&repo, &scan_cache)
+            .context(format!("Failed to update {}", git_store::STATE_REF))?;
+        let notes = git_store::write_commit_notes(&repo, &scan_cache)
+            .context(format!("Failed to update {}", git_store::NOTES_REF))?;
+        println!("Stored scan state in {} and {} commit notes in {}", git_store::STATE_REF, notes, git_store::NOTES_REF);
+    } else {
+        // Write updated cache to file
+        let updated_cache_content = serde_json::to_string_pretty(&scan_cache)
+            .context(format!("Failed to serialize {}", cache_file_path.display()))?;
+        fs::write(&cache_file_path, updated_cache_content)
+            .context(format!("Failed to write {}", cache_file_path.display()))?;
+    }
 
-    println!("Scan complete for repository: {}", repo_to_scan_path.display());
-    println!("Total CRQ links found: {}", scan_cache.found_crq_links.len());
-    println!("Total URLs found: {}", scan_cache.found_urls.len());
-    println!("Total terms found: {}", scan_cache.found_terms.len());
+    match &args.manifest {
+        Some(manifest_path) => println!("Scan complete for manifest: {} ({} repositories)", manifest_path.display(), repositories.len()),
+        None => println!("Scan complete for repository: {}", args.repo_to_scan_path.display()),
+    }
+    println!("Total CRQ links found: {}", scan_cache.crq_links.len());
+    println!("Total URLs found: {}", scan_cache.urls.len());
+    println!("Total terms found: {}", scan_cache.terms.len());
+    for name in repositories.iter().filter_map(|(name, _)| name.as_deref()) {
+        let mentions = |findings: &BTreeMap<String, Finding>| findings.values()
+            .filter(|finding| finding.occurrences.iter().any(|o| o.repository.as_deref() == Some(name)))
+            .count();
+        println!("  {}: {} CRQ links, {} URLs, {} terms", name, mentions(&scan_cache.crq_links), mentions(&scan_cache.urls), mentions(&scan_cache.terms));
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,65 @@
+use glob::{MatchOptions, Pattern, PatternError};
+use serde::{Serialize, Deserialize};
+
+/// Lockfiles and similar machine-written files that only add noise to the scan.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "**/Cargo.lock",
+    "**/package-lock.json",
+    "**/yarn.lock",
+    "**/*.min.js",
+    "**/.wikidata_cache/**",
+];
+
+/// Blobs larger than this are skipped unless the config says otherwise.
+pub const DEFAULT_MAX_BLOB_SIZE: u64 = 512 * 1024;
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Path filtering rules, as read from the scanner config file.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct PathFilterConfig {
+    /// When non-empty, only paths matching one of these globs are scanned.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Paths matching any of these globs are skipped, in addition to `DEFAULT_EXCLUDES`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub max_blob_size: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    pub max_blob_size: u64,
+}
+
+impl PathFilter {
+    pub fn new(config: &PathFilterConfig) -> Result<Self, PatternError> {
+        let include = config.include.iter()
+            .map(|glob| Pattern::new(glob))
+            .collect::<Result<Vec<_>, _>>()?;
+        let exclude = DEFAULT_EXCLUDES.iter()
+            .map(|glob| glob.to_string())
+            .chain(config.exclude.iter().cloned())
+            .map(|glob| Pattern::new(&glob))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PathFilter {
+            include,
+            exclude,
+            max_blob_size: config.max_blob_size.unwrap_or(DEFAULT_MAX_BLOB_SIZE),
+        })
+    }
+
+    /// Whether `path` (relative to the repository root, `/`-separated) passes the include and exclude globs.
+    pub fn is_included(&self, path: &str) -> bool {
+        let included = self.include.is_empty()
+            || self.include.iter().any(|pattern| pattern.matches_with(path, MATCH_OPTIONS));
+        included && !self.exclude.iter().any(|pattern| pattern.matches_with(path, MATCH_OPTIONS))
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::{Result, Context};
+use git2::{Index, Repository};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::history_scanner::{staged_added_lines, ScanOptions, CRQ_REGEX, URL_REGEX};
+use crate::url_normalizer::{canonicalize_url, classify_url, trim_url, UrlClass};
+
+/// Marks hook scripts written by `install_hooks`, so they can be replaced without `force`.
+const HOOK_MARKER: &str = "# Installed by wikidata-tool";
+
+/// Rules enforced by the git hooks, read from the `policy` section of the scanner config.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// Commit messages must mention a CRQ id that has a document in `crq_docs_dir`.
+    pub require_crq_reference: bool,
+    /// Directory, relative to the repository root, holding one `CRQ-<n>-*.md` file per CRQ.
+    pub crq_docs_dir: String,
+    /// Wikipedia URLs added by a commit must already be in canonical form.
+    pub require_canonical_wikipedia_urls: bool,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        PolicyConfig {
+            require_crq_reference: true,
+            crq_docs_dir: "docs".to_string(),
+            require_canonical_wikipedia_urls: true,
+        }
+    }
+}
+
+/// A broken rule, with the staged line that broke it if there is one.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PolicyViolation {
+    pub location: Option<(String, u32)>,
+    pub message: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some((path, line)) => write!(f, "{}:{}: {}", path, line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Makes `repo` read the index named by `GIT_INDEX_FILE`, if set. Git points hooks at a
+/// temporary index for `git commit -a` and `git commit <paths>`, which libgit2 doesn't pick up
+/// on its own. Relative paths are taken from the working directory root, where hooks run.
+pub fn use_hook_index(repo: &Repository) -> Result<()> {
+    let Some(index_file) = std::env::var_os("GIT_INDEX_FILE") else {
+        return Ok(());
+    };
+    let index_path = repo.workdir().unwrap_or(repo.path()).join(index_file);
+    let mut index = Index::open(&index_path)
+        .context(format!("Failed to open index: {}", index_path.display()))?;
+    repo.set_index(&mut index)?;
+    Ok(())
+}
+
+/// The CRQ ids that have a document in `docs_dir`, as staged in the index, so a CRQ written in
+/// the same commit counts.
+pub fn known_crqs(repo: &Repository, docs_dir: &str) -> Result<BTreeSet<String>> {
+    let prefix = format!("{}/", docs_dir.trim_end_matches('/'));
+    let index = repo.index()?;
+    let crqs = index.iter()
+        .filter_map(|entry| {
+            let path = String::from_utf8_lossy(&entry.path).into_owned();
+            let file_name = path.strip_prefix(&prefix)?.rsplit('/').next()?.to_string();
+            CRQ_REGEX.find(&file_name).map(|m| m.as_str().to_string())
+        })
+        .collect();
+    Ok(crqs)
+}
+
+/// Checks a commit message against `config`. Comment lines are ignored, as git strips them, and
+/// so are merge commits.
+pub fn check_commit_message(message: &str, config: &PolicyConfig, known_crqs: &BTreeSet<String>) -> Vec<PolicyViolation> {
+    let message: String = message.lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !config.require_crq_reference || message.starts_with("Merge ") {
+        return Vec::new();
+    }
+
+    let referenced: Vec<&str> = CRQ_REGEX.find_iter(&message).map(|m| m.as_str()).collect();
+    if referenced.is_empty() {
+        return vec![PolicyViolation { location: None, message: "Commit message must reference a CRQ (e.g. CRQ-059)".to_string() }];
+    }
+    if referenced.iter().any(|crq| known_crqs.contains(*crq)) {
+        return Vec::new();
+    }
+    vec![PolicyViolation {
+        location: None,
+        message: format!("{} has no document in {}/", referenced.join(", "), config.crq_docs_dir),
+    }]
+}
+
+/// Checks the lines added by staged changes against `config`.
+pub fn check_staged(repo: &Repository, options: &ScanOptions, config: &PolicyConfig) -> Result<Vec<PolicyViolation>> {
+    let mut violations = Vec::new();
+    if !config.require_canonical_wikipedia_urls {
+        return Ok(violations);
+    }
+    for (path, line, content) in staged_added_lines(repo, options)? {
+        for m in URL_REGEX.find_iter(&content) {
+            let Some(canonical) = canonicalize_url(m.as_str()) else {
+                continue;
+            };
+            let is_wikipedia = matches!(classify_url(&canonical), UrlClass::WikipediaArticle { .. });
+            // Canonical URLs carry no fragment, but links to a section are fine
+            let written = trim_url(m.as_str());
+            let (article, fragment) = match written.split_once('#') {
+                Some((article, fragment)) => (article, Some(fragment)),
+                None => (written, None),
+            };
+            if is_wikipedia && article != canonical {
+                let suggestion = match fragment {
+                    Some(fragment) => format!("{}#{}", canonical, fragment),
+                    None => canonical,
+                };
+                violations.push(PolicyViolation {
+                    location: Some((path.clone(), line)),
+                    message: format!("Wikipedia URL {} is not canonical; use {}", written, suggestion),
+                });
+            }
+        }
+    }
+    Ok(violations)
+}
+
+/// Writes `pre-commit` and `commit-msg` hooks that run `executable hooks pre-commit` and
+/// `executable hooks commit-msg`. Hooks not written by this function are only replaced with
+/// `force`. Returns the paths written.
+pub fn install_hooks(repo: &Repository, executable: &Path, force: bool) -> Result<Vec<PathBuf>> {
+    let hooks_dir = match repo.config()?.get_path("core.hooksPath") {
+        Ok(path) if path.is_absolute() => path,
+        Ok(path) => repo.workdir().unwrap_or(repo.path()).join(path),
+        Err(_) => repo.path().join("hooks"),
+    };
+    fs::create_dir_all(&hooks_dir)
+        .context(format!("Failed to create hooks directory: {}", hooks_dir.display()))?;
+
+    let mut written = Vec::new();
+    for (hook, arguments) in [("pre-commit", "hooks pre-commit"), ("commit-msg", "hooks commit-msg \"$1\"")] {
+        let hook_path = hooks_dir.join(hook);
+        if hook_path.exists() && !force {
+            let existing = fs::read_to_string(&hook_path).unwrap_or_default();
+            if !existing.contains(HOOK_MARKER) {
+                anyhow::bail!("{} already exists and wasn't installed by wikidata-tool; use --force to replace it", hook_path.display());
+            }
+        }
+        let script = format!("#!/bin/sh\n{}\nexec \"{}\" {}\n", HOOK_MARKER, executable.display(), arguments);
+        fs::write(&hook_path, script)
+            .context(format!("Failed to write {}", hook_path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+        }
+        written.push(hook_path);
+    }
+    Ok(written)
+}
@@ -0,0 +1,86 @@
+use reqwest::Client;
+
+use crate::scan_cache::ScanCache;
+use crate::url_normalizer::UrlClass;
+use crate::wikidata_client::{fetch_and_cache_wikidata_entity, search_wikidata_entities};
+use crate::wikipedia_parser::{fetch_and_cache_wikipedia_article, CleaningOptions};
+
+/// User agent sent to Wikipedia and Wikidata, as their API etiquette asks for one.
+pub const USER_AGENT: &str = concat!("wikidata-tool/", env!("CARGO_PKG_VERSION"), " (https://github.com/meta-introspector/wikidata-tool-repo)");
+
+/// Title prefixes of Wikipedia pages that aren't articles and have no Wikidata item of their own.
+const NON_ARTICLE_NAMESPACES: &[&str] = &[
+    "Special:", "Wikipedia:", "File:", "Category:", "Template:", "Help:", "Portal:", "Talk:",
+];
+
+/// Fetches and caches the article and entity behind every Wikipedia or Wikidata URL finding that
+/// isn't resolved yet, and records the entity's QID on the finding. A URL that fails is reported
+/// and left for the next run, so one unreachable page doesn't hold up the rest. Returns the
+/// number of findings resolved.
+pub async fn resolve_urls(client: &Client, scan_cache: &mut ScanCache, options: &CleaningOptions) -> usize {
+    let mut resolved = 0;
+    for finding in scan_cache.urls.values_mut().filter(|finding| finding.wikidata_id.is_none()) {
+        let result = match &finding.url_class {
+            Some(UrlClass::WikipediaArticle { title, .. }) if NON_ARTICLE_NAMESPACES.iter().any(|ns| title.starts_with(ns)) => continue,
+            Some(UrlClass::WikipediaArticle { language, title }) => resolve_article(client, &finding.value, language, title, options).await,
+            Some(UrlClass::WikidataEntity { id }) => resolve_entity(client, id).await,
+            _ => continue,
+        };
+        match result {
+            Ok(Some(id)) => {
+                finding.wikidata_id = Some(id);
+                resolved += 1;
+            },
+            Ok(None) => println!("No Wikidata entity found for {}", finding.value),
+            Err(e) => eprintln!("Failed to resolve {}: {}", finding.value, e),
+        }
+    }
+    resolved
+}
+
+async fn resolve_article(client: &Client, url: &str, language: &str, title: &str, options: &CleaningOptions) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    // Canonical titles use underscores; page headings and the Wikidata sitelinks use spaces
+    let title = title.replace('_', " ");
+    fetch_and_cache_wikipedia_article(client, url, language, &title, options).await?;
+    // Wikidata site ids use underscores where language codes use hyphens, e.g. `zh_yuewiki`
+    let site = format!("{}wiki", language.replace('-', "_"));
+    let entity = fetch_and_cache_wikidata_entity(client, &site, Some(&title), None).await?;
+    Ok(entity.map(|entity| entity.id))
+}
+
+async fn resolve_entity(client: &Client, id: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    // Only items are fetched; properties and lexemes have no article or fact list to cache
+    if !id.starts_with('Q') {
+        return Ok(None);
+    }
+    let entity = fetch_and_cache_wikidata_entity(client, "enwiki", None, Some(id)).await?;
+    Ok(entity.map(|entity| entity.id))
+}
+
+/// Searches Wikidata for up to `max_terms` unresolved terms, most mentioned first, and records
+/// the best candidate's QID on each term along with the other candidates. Terms without any
+/// candidate are left unresolved. Returns the number of terms resolved.
+pub async fn resolve_terms(client: &Client, api_url: &str, scan_cache: &mut ScanCache, max_terms: usize) -> usize {
+    let mut pending: Vec<_> = scan_cache.terms.values_mut()
+        .filter(|finding| finding.wikidata_id.is_none())
+        .collect();
+    pending.sort_by_key(|finding| std::cmp::Reverse(finding.occurrences.len()));
+
+    let mut resolved = 0;
+    for finding in pending.into_iter().take(max_terms) {
+        match search_wikidata_entities(client, api_url, &finding.value, "en", 5).await {
+            Ok(candidates) => {
+                let mut candidates = candidates.into_iter();
+                let Some(best) = candidates.next() else {
+                    println!("No Wikidata candidates for term '{}'", finding.value);
+                    continue;
+                };
+                finding.wikidata_id = Some(best.id);
+                finding.alternatives = candidates.collect();
+                resolved += 1;
+            },
+            Err(e) => eprintln!("Failed to search Wikidata for term '{}': {}", finding.value, e),
+        }
+    }
+    resolved
+}
@@ -0,0 +1,246 @@
+use proc_macro2::{TokenStream, TokenTree};
+use syn::visit::{self, Visit};
+use syn::{Attribute, Expr, ExprLit, Lit, LitStr, Meta};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RustFragmentKind {
+    DocComment,
+    LineComment,
+    StringLiteral,
+    ItemName,
+}
+
+/// A piece of a Rust source file worth indexing, with the 1-based line it starts on.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RustFragment {
+    pub kind: RustFragmentKind,
+    pub text: String,
+    pub line: u32,
+}
+
+/// Parses `source` with `syn` and returns its doc comments, line comments, string literals
+/// (including those inside macro invocations) and item names, in no particular order.
+/// Returns `None` when the file doesn't parse, so callers can fall back to plain text.
+pub fn extract_rust_fragments(source: &str) -> Option<Vec<RustFragment>> {
+    let parsed = syn::parse_file(source).map(|file| {
+        let mut visitor = FragmentVisitor { fragments: Vec::new() };
+        visitor.visit_file(&file);
+        visitor.fragments
+    });
+    // With span locations enabled, proc-macro2 keeps every parsed source in a thread-local map
+    // for the life of the thread; nothing holds a span past this point, so release it
+    proc_macro2::extra::invalidate_current_thread_spans();
+    let mut fragments = parsed.ok()?;
+    fragments.extend(extract_line_comments(source));
+    Some(fragments)
+}
+
+struct FragmentVisitor {
+    fragments: Vec<RustFragment>,
+}
+
+impl FragmentVisitor {
+    fn push(&mut self, kind: RustFragmentKind, text: String, line: usize) {
+        let text = text.trim().to_string();
+        if !text.is_empty() {
+            self.fragments.push(RustFragment { kind, text, line: line as u32 });
+        }
+    }
+
+    fn push_ident(&mut self, ident: &syn::Ident) {
+        self.push(RustFragmentKind::ItemName, ident.to_string(), ident.span().start().line);
+    }
+
+    /// Macro bodies are opaque to `syn`, so string literals are picked out of the raw tokens.
+    fn visit_tokens(&mut self, tokens: TokenStream) {
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => self.visit_tokens(group.stream()),
+                TokenTree::Literal(literal) => {
+                    if let Ok(lit) = syn::parse_str::<LitStr>(&literal.to_string()) {
+                        self.push(RustFragmentKind::StringLiteral, lit.value(), literal.span().start().line);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for FragmentVisitor {
+    fn visit_attribute(&mut self, attr: &'ast Attribute) {
+        // `///` and `//!` comments arrive as `#[doc = "..."]` attributes
+        if let Meta::NameValue(meta) = &attr.meta {
+            if meta.path.is_ident("doc") {
+                if let Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) = &meta.value {
+                    self.push(RustFragmentKind::DocComment, lit.value(), lit.span().start().line);
+                    return;
+                }
+            }
+        }
+        visit::visit_attribute(self, attr);
+    }
+
+    fn visit_lit_str(&mut self, lit: &'ast LitStr) {
+        self.push(RustFragmentKind::StringLiteral, lit.value(), lit.span().start().line);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        self.visit_tokens(mac.tokens.clone());
+        visit::visit_macro(self, mac);
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        self.push_ident(&item.sig.ident);
+        visit::visit_item_fn(self, item);
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
+        self.push_ident(&item.sig.ident);
+        visit::visit_impl_item_fn(self, item);
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast syn::TraitItemFn) {
+        self.push_ident(&item.sig.ident);
+        visit::visit_trait_item_fn(self, item);
+    }
+
+    fn visit_item_struct(&mut self, item: &'ast syn::ItemStruct) {
+        self.push_ident(&item.ident);
+        visit::visit_item_struct(self, item);
+    }
+
+    fn visit_item_enum(&mut self, item: &'ast syn::ItemEnum) {
+        self.push_ident(&item.ident);
+        visit::visit_item_enum(self, item);
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast syn::ItemTrait) {
+        self.push_ident(&item.ident);
+        visit::visit_item_trait(self, item);
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        self.push_ident(&item.ident);
+        visit::visit_item_mod(self, item);
+    }
+
+    fn visit_item_const(&mut self, item: &'ast syn::ItemConst) {
+        self.push_ident(&item.ident);
+        visit::visit_item_const(self, item);
+    }
+
+    fn visit_item_static(&mut self, item: &'ast syn::ItemStatic) {
+        self.push_ident(&item.ident);
+        visit::visit_item_static(self, item);
+    }
+
+    fn visit_item_type(&mut self, item: &'ast syn::ItemType) {
+        self.push_ident(&item.ident);
+        visit::visit_item_type(self, item);
+    }
+}
+
+/// `syn` discards ordinary comments, so `//` and `/* */` comments are found with a small lexer
+/// that knows enough about string, raw string and char literals not to be fooled by them.
+/// Doc comments are skipped here because the parser already reports them.
+fn extract_line_comments(source: &str) -> Vec<RustFragment> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut fragments = Vec::new();
+    let mut line = 1u32;
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\n' => line += 1,
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                let start = i + 2;
+                let end = chars[start..].iter().position(|&c| c == '\n').map_or(chars.len(), |p| start + p);
+                let is_doc = matches!(chars.get(start), Some('/') | Some('!')) && chars.get(start + 1) != Some(&'/');
+                if !is_doc {
+                    let text: String = chars[start..end].iter().collect::<String>().trim_start_matches('/').trim().to_string();
+                    if !text.is_empty() {
+                        fragments.push(RustFragment { kind: RustFragmentKind::LineComment, text, line });
+                    }
+                }
+                i = end;
+                continue;
+            },
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let start_line = line;
+                let is_doc = matches!(chars.get(i + 2), Some('*') | Some('!')) && chars.get(i + 3) != Some(&'/');
+                let mut depth = 1;
+                let mut j = i + 2;
+                while j < chars.len() && depth > 0 {
+                    if chars[j] == '/' && chars.get(j + 1) == Some(&'*') {
+                        depth += 1;
+                        j += 1;
+                    } else if chars[j] == '*' && chars.get(j + 1) == Some(&'/') {
+                        depth -= 1;
+                        j += 1;
+                    } else if chars[j] == '\n' {
+                        line += 1;
+                    }
+                    j += 1;
+                }
+                if !is_doc {
+                    let body_end = j.saturating_sub(2).max(i + 2);
+                    let text = chars[i + 2..body_end].iter().collect::<String>().trim().to_string();
+                    if !text.is_empty() {
+                        fragments.push(RustFragment { kind: RustFragmentKind::LineComment, text, line: start_line });
+                    }
+                }
+                i = j;
+                continue;
+            },
+            'r' if matches!(chars.get(i + 1), Some('"') | Some('#')) && (i == 0 || !is_ident_char(chars[i - 1])) => {
+                let hashes = chars[i + 1..].iter().take_while(|&&c| c == '#').count();
+                if chars.get(i + 1 + hashes) == Some(&'"') {
+                    let mut j = i + 2 + hashes;
+                    while j < chars.len() {
+                        if chars[j] == '\n' {
+                            line += 1;
+                        } else if chars[j] == '"' && chars[j + 1..].iter().take(hashes).filter(|&&c| c == '#').count() == hashes {
+                            j += 1 + hashes;
+                            break;
+                        }
+                        j += 1;
+                    }
+                    i = j;
+                    continue;
+                }
+            },
+            '"' => {
+                let mut j = i + 1;
+                while j < chars.len() && chars[j] != '"' {
+                    if chars[j] == '\\' {
+                        j += 1;
+                    }
+                    if chars.get(j) == Some(&'\n') {
+                        line += 1;
+                    }
+                    j += 1;
+                }
+                i = j + 1;
+                continue;
+            },
+            // A char literal such as '"' or '\''; anything else after a quote is a lifetime
+            '\'' if chars.get(i + 1) == Some(&'\\') => {
+                let end = chars[i + 2..].iter().position(|&c| c == '\'').map_or(chars.len(), |p| i + 3 + p);
+                i = end;
+                continue;
+            },
+            '\'' if chars.get(i + 2) == Some(&'\'') => {
+                i += 3;
+                continue;
+            },
+            _ => {},
+        }
+        i += 1;
+    }
+    fragments
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
@@ -0,0 +1,415 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Serialize, Deserialize};
+
+use crate::data_structures::EntityCandidate;
+use crate::url_normalizer::{classify_url, UrlClass};
+
+/// Bumped whenever the on-disk layout of `ScanCache` changes; older caches are rescanned from scratch.
+pub const SCAN_CACHE_VERSION: u32 = 11;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    CrqLink,
+    Url,
+    Term,
+    DocComment,
+    LineComment,
+    StringLiteral,
+    ItemName,
+}
+
+/// How merge commits are diffed when attributing added lines.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffMode {
+    /// Follow first parents only; a merge is credited with everything it brought in.
+    FirstParent,
+    /// Walk every commit; a merge is only credited with lines new relative to all its parents.
+    #[default]
+    Combined,
+    /// Walk every commit but ignore merges entirely.
+    SkipMerges,
+}
+
+/// Whether a finding is still present in the scanned history's latest commits.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingStatus {
+    #[default]
+    Live,
+    /// Every recorded occurrence has since been deleted.
+    Removed,
+}
+
+/// A single place where a finding was introduced, or deleted when listed in `Finding::removals`.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Occurrence {
+    pub commit: String,
+    pub path: String,
+    pub line: Option<u32>,
+    pub author: String,
+    pub timestamp: i64,
+    /// Name of the repository the commit belongs to, when scanning a manifest of repositories.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// Path of the submodule the commit belongs to, relative to the top-level repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submodule: Option<String>,
+    /// The diff mode that attributed this occurrence to `commit`.
+    #[serde(default)]
+    pub diff_mode: DiffMode,
+    /// Where the file has since been renamed to, as a full path; `None` while it hasn't moved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_path: Option<String>,
+    /// For an occurrence carried into a copied file, the full path of the file it was copied from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copied_from: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub value: String,
+    pub first_seen_commit: String,
+    pub last_seen_commit: String,
+    pub occurrences: Vec<Occurrence>,
+    /// Set for URL findings, whose value is the canonical URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_class: Option<UrlClass>,
+    /// The Wikidata entity a Wikipedia or Wikidata URL, or a term, resolved to, once resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wikidata_id: Option<String>,
+    /// For terms, the other search candidates that `wikidata_id` was chosen over, best first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<EntityCandidate>,
+    /// Number of live occurrences per file (including the submodule prefix), following renames:
+    /// occurrences added minus those deleted. Files left with none are dropped.
+    #[serde(default)]
+    pub counts_by_file: BTreeMap<String, u64>,
+    /// Number of occurrences each commit added; deletions don't lower it.
+    #[serde(default)]
+    pub counts_by_commit: BTreeMap<String, u64>,
+    /// Places where a line mentioning the finding was deleted, oldest first. Paths and line
+    /// numbers refer to the parent side of the diff.
+    #[serde(default)]
+    pub removals: Vec<Occurrence>,
+    /// Occurrences added minus occurrences deleted, never below zero.
+    #[serde(default)]
+    pub live_count: u64,
+    #[serde(default)]
+    pub status: FindingStatus,
+    /// The commit that deleted the last live occurrence, while `status` is `Removed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removed_in_commit: Option<String>,
+    /// Where the finding appears at HEAD, with the commit and author `git blame` credits each
+    /// line to. Only filled in by a blame pass.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blame: Vec<Occurrence>,
+}
+
+impl Occurrence {
+    /// The path relative to the top-level repository, prefixed with the submodule path if any,
+    /// and with `<repository>:` when the occurrence comes from a manifest scan.
+    pub fn full_path(&self) -> String {
+        let path = match &self.submodule {
+            Some(submodule) => format!("{}/{}", submodule, self.path),
+            None => self.path.clone(),
+        };
+        match &self.repository {
+            Some(repository) => format!("{}:{}", repository, path),
+            None => path,
+        }
+    }
+
+    /// The full path of the file this occurrence lives in today, following renames.
+    pub fn latest_path(&self) -> String {
+        self.current_path.clone().unwrap_or_else(|| self.full_path())
+    }
+}
+
+impl Finding {
+    fn count(&mut self, occurrence: &Occurrence) {
+        *self.counts_by_file.entry(occurrence.latest_path()).or_insert(0) += 1;
+        *self.counts_by_commit.entry(occurrence.commit.clone()).or_insert(0) += 1;
+    }
+
+    /// Takes a deleted line off the per-file count of the file it was deleted from. Removals are
+    /// recorded with the parent-side path, which a rename in the same commit has already moved
+    /// the count away from; a path without a count falls back to `renamed_to`, where that file
+    /// lives now (see `ScanCache::renamed_paths`).
+    fn uncount(&mut self, removal: &Occurrence, renamed_to: Option<&str>) {
+        let mut path = removal.full_path();
+        if !self.counts_by_file.contains_key(&path) {
+            let Some(renamed_to) = renamed_to else {
+                return;
+            };
+            path = renamed_to.to_string();
+        }
+        if let Some(count) = self.counts_by_file.get_mut(&path) {
+            *count -= 1;
+            if *count == 0 {
+                self.counts_by_file.remove(&path);
+            }
+        }
+    }
+
+    /// Rebuilds the per-file and per-commit counts from the occurrences and removals.
+    fn recount(&mut self, renamed_paths: &BTreeMap<String, String>) {
+        self.counts_by_file.clear();
+        self.counts_by_commit.clear();
+        for occurrence in std::mem::take(&mut self.occurrences) {
+            self.count(&occurrence);
+            self.occurrences.push(occurrence);
+        }
+        for removal in std::mem::take(&mut self.removals) {
+            self.uncount(&removal, renamed_paths.get(&removal.full_path()).map(String::as_str));
+            self.removals.push(removal);
+        }
+    }
+
+    /// Recomputes the live count and status from the recorded occurrences and removals.
+    fn update_status(&mut self) {
+        self.live_count = (self.occurrences.len() as u64).saturating_sub(self.removals.len() as u64);
+        if self.live_count > 0 {
+            self.status = FindingStatus::Live;
+            self.removed_in_commit = None;
+        } else {
+            self.status = FindingStatus::Removed;
+            self.removed_in_commit = self.removals.last().map(|removal| removal.commit.clone());
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ScanCache {
+    #[serde(default)]
+    pub version: u32,
+    pub last_scanned_commit: Option<String>,
+    /// Last scanned commit of each reference other than HEAD, keyed by full reference name.
+    #[serde(default)]
+    pub ref_watermarks: BTreeMap<String, String>,
+    /// Last scanned commit of each submodule, keyed by submodule path.
+    #[serde(default)]
+    pub submodule_watermarks: BTreeMap<String, String>,
+    /// Where each renamed file now lives, keyed by its old full path, following later renames.
+    #[serde(default)]
+    pub renamed_paths: BTreeMap<String, String>,
+    #[serde(default)]
+    pub crq_links: BTreeMap<String, Finding>,
+    #[serde(default)]
+    pub urls: BTreeMap<String, Finding>,
+    #[serde(default)]
+    pub terms: BTreeMap<String, Finding>,
+    #[serde(default)]
+    pub doc_comments: BTreeMap<String, Finding>,
+    #[serde(default)]
+    pub line_comments: BTreeMap<String, Finding>,
+    #[serde(default)]
+    pub string_literals: BTreeMap<String, Finding>,
+    #[serde(default)]
+    pub item_names: BTreeMap<String, Finding>,
+}
+
+impl ScanCache {
+    pub fn new() -> Self {
+        ScanCache { version: SCAN_CACHE_VERSION, ..Default::default() }
+    }
+
+    pub fn is_current(&self) -> bool {
+        self.version == SCAN_CACHE_VERSION
+    }
+
+    fn findings_mut(&mut self, kind: FindingKind) -> &mut BTreeMap<String, Finding> {
+        match kind {
+            FindingKind::CrqLink => &mut self.crq_links,
+            FindingKind::Url => &mut self.urls,
+            FindingKind::Term => &mut self.terms,
+            FindingKind::DocComment => &mut self.doc_comments,
+            FindingKind::LineComment => &mut self.line_comments,
+            FindingKind::StringLiteral => &mut self.string_literals,
+            FindingKind::ItemName => &mut self.item_names,
+        }
+    }
+
+    /// Records an occurrence of `value`. Commits are expected to be recorded oldest first,
+    /// so the first occurrence fixes `first_seen_commit` and each later one moves `last_seen_commit`.
+    pub fn record(&mut self, kind: FindingKind, value: &str, occurrence: Occurrence) {
+        let finding = self.findings_mut(kind)
+            .entry(value.to_string())
+            .or_insert_with(|| Finding {
+                kind,
+                value: value.to_string(),
+                first_seen_commit: occurrence.commit.clone(),
+                last_seen_commit: occurrence.commit.clone(),
+                occurrences: Vec::new(),
+                url_class: (kind == FindingKind::Url).then(|| classify_url(value)),
+                wikidata_id: None,
+                alternatives: Vec::new(),
+                counts_by_file: BTreeMap::new(),
+                counts_by_commit: BTreeMap::new(),
+                removals: Vec::new(),
+                live_count: 0,
+                status: FindingStatus::Live,
+                removed_in_commit: None,
+                blame: Vec::new(),
+            });
+        finding.last_seen_commit = occurrence.commit.clone();
+        finding.count(&occurrence);
+        finding.occurrences.push(occurrence);
+        finding.update_status();
+    }
+
+    /// Records the deletion of a line mentioning `value`. Once every occurrence has been deleted
+    /// the finding is marked `Removed` in `occurrence.commit`; adding it again revives it.
+    /// Deletions of values that were never recorded (e.g. outside an ad-hoc scan's range) are ignored.
+    pub fn record_removal(&mut self, kind: FindingKind, value: &str, occurrence: Occurrence) {
+        let renamed_to = self.renamed_paths.get(&occurrence.full_path()).cloned();
+        let Some(finding) = self.findings_mut(kind).get_mut(value) else {
+            return;
+        };
+        finding.uncount(&occurrence, renamed_to.as_deref());
+        finding.removals.push(occurrence);
+        finding.update_status();
+    }
+
+    /// Forgets the blame recorded for `repository`, ahead of a fresh blame pass.
+    pub fn clear_blame(&mut self, repository: Option<&str>) {
+        let all_findings = [
+            &mut self.crq_links, &mut self.urls, &mut self.terms, &mut self.doc_comments,
+            &mut self.line_comments, &mut self.string_literals, &mut self.item_names,
+        ];
+        for findings in all_findings {
+            for finding in findings.values_mut() {
+                finding.blame.retain(|o| o.repository.as_deref() != repository);
+            }
+        }
+    }
+
+    /// Attaches a blamed line to an existing finding; values the history scan never saw are ignored.
+    pub fn record_blame(&mut self, kind: FindingKind, value: &str, occurrence: Occurrence) {
+        if let Some(finding) = self.findings_mut(kind).get_mut(value) {
+            finding.blame.push(occurrence);
+        }
+    }
+
+    /// Paths, relative to the top-level repository, of the files that hold occurrences of live
+    /// findings in `repository`. Submodule occurrences are left out.
+    pub fn live_paths(&self, repository: Option<&str>) -> BTreeSet<String> {
+        let prefix = repository.map(|repository| format!("{}:", repository)).unwrap_or_default();
+        [
+            &self.crq_links, &self.urls, &self.terms, &self.doc_comments,
+            &self.line_comments, &self.string_literals, &self.item_names,
+        ]
+            .into_iter()
+            .flat_map(|findings| findings.values())
+            .filter(|finding| finding.status == FindingStatus::Live)
+            .flat_map(|finding| &finding.occurrences)
+            .filter(|o| o.repository.as_deref() == repository && o.submodule.is_none())
+            .filter_map(|o| o.latest_path().strip_prefix(&prefix).map(str::to_string))
+            .collect()
+    }
+
+    /// Returns the CRQ references and URLs that were once mentioned but no longer are.
+    pub fn removed_links(&self) -> Vec<&Finding> {
+        self.crq_links.values()
+            .chain(self.urls.values())
+            .filter(|finding| finding.status == FindingStatus::Removed)
+            .collect()
+    }
+
+    /// Carries provenance across a file rename: occurrences in `old_path` now live in
+    /// `new_path`, and per-file counts move with them. Both are full paths.
+    pub fn record_rename(&mut self, old_path: &str, new_path: &str) {
+        for latest_path in self.renamed_paths.values_mut() {
+            if latest_path == old_path {
+                *latest_path = new_path.to_string();
+            }
+        }
+        self.renamed_paths.remove(new_path);
+        self.renamed_paths.insert(old_path.to_string(), new_path.to_string());
+
+        let all_findings = [
+            &mut self.crq_links, &mut self.urls, &mut self.terms, &mut self.doc_comments,
+            &mut self.line_comments, &mut self.string_literals, &mut self.item_names,
+        ];
+        for findings in all_findings {
+            for finding in findings.values_mut() {
+                let Some(count) = finding.counts_by_file.remove(old_path) else {
+                    continue;
+                };
+                *finding.counts_by_file.entry(new_path.to_string()).or_insert(0) += count;
+                for occurrence in &mut finding.occurrences {
+                    if occurrence.latest_path() == old_path {
+                        occurrence.current_path = Some(new_path.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Carries findings into a copied file: each live occurrence in `old_path` (a full path) gains
+    /// a counterpart shaped like `copy`, which names the copying commit and the new file.
+    /// `old_path` keeps its own occurrences.
+    pub fn record_copy(&mut self, old_path: &str, copy: Occurrence) {
+        let all_findings = [
+            &mut self.crq_links, &mut self.urls, &mut self.terms, &mut self.doc_comments,
+            &mut self.line_comments, &mut self.string_literals, &mut self.item_names,
+        ];
+        for findings in all_findings {
+            for finding in findings.values_mut() {
+                let Some(&count) = finding.counts_by_file.get(old_path) else {
+                    continue;
+                };
+                for _ in 0..count {
+                    let occurrence = Occurrence { copied_from: Some(old_path.to_string()), ..copy.clone() };
+                    finding.count(&occurrence);
+                    finding.occurrences.push(occurrence);
+                }
+                finding.last_seen_commit = copy.commit.clone();
+                finding.update_status();
+            }
+        }
+    }
+
+    /// Drops the occurrences and removals recorded for `submodule` (`None` for the top-level
+    /// repository) of `repository` that `keep` rejects, removing findings left without any occurrence.
+    pub fn retain_occurrences<F>(&mut self, repository: Option<&str>, submodule: Option<&str>, keep: F)
+    where
+        F: Fn(&Occurrence) -> bool,
+    {
+        let in_scope = |o: &Occurrence| o.repository.as_deref() == repository && o.submodule.as_deref() == submodule;
+        let renamed_paths = &self.renamed_paths;
+        let all_findings = [
+            &mut self.crq_links, &mut self.urls, &mut self.terms, &mut self.doc_comments,
+            &mut self.line_comments, &mut self.string_literals, &mut self.item_names,
+        ];
+        for findings in all_findings {
+            findings.retain(|_, finding| {
+                finding.occurrences.retain(|o| !in_scope(o) || keep(o));
+                finding.removals.retain(|o| !in_scope(o) || keep(o));
+                finding.update_status();
+                finding.recount(renamed_paths);
+                match (finding.occurrences.first(), finding.occurrences.last()) {
+                    (Some(first), Some(last)) => {
+                        finding.first_seen_commit = first.commit.clone();
+                        finding.last_seen_commit = last.commit.clone();
+                        true
+                    },
+                    _ => false,
+                }
+            });
+        }
+    }
+
+    /// Returns every finding (of any kind) whose value matches `value` exactly.
+    pub fn query(&self, value: &str) -> Vec<&Finding> {
+        [
+            &self.crq_links, &self.urls, &self.terms, &self.doc_comments,
+            &self.line_comments, &self.string_literals, &self.item_names,
+        ]
+            .into_iter()
+            .filter_map(|findings| findings.get(value))
+            .collect()
+    }
+}
@@ -0,0 +1,96 @@
+use regex::Regex;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref WORD_REGEX: Regex = Regex::new(r"\b[a-zA-Z_][a-zA-Z0-9_]*\b").unwrap();
+}
+
+/// Keywords of Rust and the other languages that show up in our repositories.
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "def", "class", "import", "from", "function", "var", "null", "none", "nil",
+    "void", "int", "bool", "str", "string", "vec", "usize", "isize", "u8", "u16", "u32", "u64",
+    "i32", "i64", "f32", "f64", "option", "result", "some", "ok", "err", "unwrap", "clone", "new",
+    "http", "https", "www", "com", "org", "html", "json", "rs", "md",
+];
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "at", "be", "been", "but",
+    "by", "can", "could", "did", "do", "does", "each", "has", "have", "he", "her", "his", "how",
+    "into", "is", "it", "its", "may", "more", "most", "no", "not", "of", "on", "one", "only", "or",
+    "other", "our", "out", "she", "should", "so", "such", "than", "that", "the", "their", "them",
+    "then", "there", "these", "they", "this", "those", "to", "up", "was", "we", "were", "what",
+    "when", "which", "who", "will", "with", "would", "you", "your",
+];
+
+/// Turns raw text into lowercase terms suitable for Wikidata lookup: identifiers are split on
+/// snake_case, camelCase and digit boundaries, and keywords, stopwords and numbers are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct TermExtractor {
+    /// Reduce each term to a crude stem (`caching` -> `cach`, `entities` -> `entity`).
+    pub stem: bool,
+}
+
+impl TermExtractor {
+    pub fn new(stem: bool) -> Self {
+        TermExtractor { stem }
+    }
+
+    pub fn extract(&self, text: &str) -> Vec<String> {
+        WORD_REGEX.find_iter(text)
+            .flat_map(|m| split_identifier(m.as_str()))
+            .filter(|term| is_meaningful(term))
+            .map(|term| if self.stem { stem(&term) } else { term })
+            .collect()
+    }
+}
+
+/// Splits an identifier into lowercase words, e.g. `fetchHTTPEntity_v2` -> `fetch`, `http`, `entity`, `v`, `2`.
+pub fn split_identifier(identifier: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in identifier.split('_').filter(|part| !part.is_empty()) {
+        let chars: Vec<char> = part.chars().collect();
+        let mut current = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            if let Some(&prev) = i.checked_sub(1).and_then(|j| chars.get(j)) {
+                let next = chars.get(i + 1);
+                let boundary = (prev.is_lowercase() && c.is_uppercase())
+                    || (prev.is_ascii_digit() != c.is_ascii_digit())
+                    // End of an acronym: the `S` in `HTTPServer` starts a new word
+                    || (prev.is_uppercase() && c.is_uppercase() && next.is_some_and(|n| n.is_lowercase()));
+                if boundary && !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+            current.extend(c.to_lowercase());
+        }
+        if !current.is_empty() {
+            words.push(current);
+        }
+    }
+    words
+}
+
+fn is_meaningful(term: &str) -> bool {
+    term.len() > 1
+        && !term.chars().all(|c| c.is_ascii_digit())
+        && !KEYWORDS.contains(&term)
+        && !STOPWORDS.contains(&term)
+}
+
+/// A light suffix-stripping stemmer; good enough to merge plurals and verb forms.
+fn stem(term: &str) -> String {
+    if let Some(base) = term.strip_suffix("ies").filter(|base| base.len() > 2) {
+        return format!("{}y", base);
+    }
+    for suffix in ["ing", "ed", "es", "s"] {
+        if let Some(base) = term.strip_suffix(suffix) {
+            if base.len() > 2 && !term.ends_with("ss") && !term.ends_with("us") && !term.ends_with("is") {
+                return base.to_string();
+            }
+        }
+    }
+    term.to_string()
+}
@@ -0,0 +1,167 @@
+use serde::{Serialize, Deserialize};
+use url::Url;
+
+/// What a canonical URL points at, as far as the Wikipedia/Wikidata fetchers are concerned.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UrlClass {
+    WikipediaArticle { language: String, title: String },
+    WikidataEntity { id: String },
+    #[serde(rename = "github_repo")]
+    GitHubRepo { owner: String, repo: String },
+    Other,
+}
+
+/// Cleans up a URL as matched in free text and returns its canonical form: trailing punctuation
+/// and unbalanced closing brackets are stripped, hosts are lowercased, mobile and bare Wikimedia
+/// hosts are mapped to their desktop names over https, and Wikipedia/Wikidata URLs are rewritten
+/// to their `/wiki/<title>` form. Returns `None` if what's left doesn't parse as a URL.
+pub fn canonicalize_url(raw: &str) -> Option<String> {
+    let trimmed = trim_url(raw);
+    let mut url = Url::parse(trimmed).ok()?;
+    let host = url.host_str()?.to_lowercase();
+
+    if let Some((language, title)) = wikipedia_title(&url, &host) {
+        return Some(format!("https://{}.wikipedia.org/wiki/{}", language, encode_title(&title)));
+    }
+    if let Some(id) = wikidata_id(&url, &host) {
+        return Some(wikidata_url(&id));
+    }
+
+    let host = host.strip_prefix("www.").filter(|h| *h == "github.com").unwrap_or(&host).to_string();
+    url.set_host(Some(&host)).ok()?;
+    Some(url.to_string())
+}
+
+/// Classifies a URL, which should already be canonical.
+pub fn classify_url(canonical: &str) -> UrlClass {
+    let Ok(url) = Url::parse(canonical) else {
+        return UrlClass::Other;
+    };
+    let Some(host) = url.host_str().map(str::to_lowercase) else {
+        return UrlClass::Other;
+    };
+
+    if let Some((language, title)) = wikipedia_title(&url, &host) {
+        return UrlClass::WikipediaArticle { language, title };
+    }
+    if let Some(id) = wikidata_id(&url, &host) {
+        return UrlClass::WikidataEntity { id };
+    }
+    if host == "github.com" || host == "www.github.com" {
+        let mut segments = url.path_segments().into_iter().flatten().filter(|s| !s.is_empty());
+        if let (Some(owner), Some(repo)) = (segments.next(), segments.next()) {
+            return UrlClass::GitHubRepo {
+                owner: owner.to_string(),
+                repo: repo.trim_end_matches(".git").to_string(),
+            };
+        }
+    }
+    UrlClass::Other
+}
+
+/// Strips what free text tends to glue onto the end of a URL: sentence punctuation, quotes and
+/// closing brackets that have no opening partner inside the URL.
+pub fn trim_url(raw: &str) -> &str {
+    let mut url = raw.trim();
+    loop {
+        let before = url.len();
+        url = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"', '`', '*', '>']);
+        for (open, close) in [('(', ')'), ('[', ']'), ('{', '}')] {
+            if url.ends_with(close) && url.matches(close).count() > url.matches(open).count() {
+                url = &url[..url.len() - 1];
+            }
+        }
+        if url.len() == before {
+            return url;
+        }
+    }
+}
+
+/// Language and decoded title of a Wikipedia article URL (`/wiki/Title` or `index.php?title=Title`).
+fn wikipedia_title(url: &Url, host: &str) -> Option<(String, String)> {
+    let language = host.strip_suffix(".wikipedia.org")?;
+    let language = language.strip_suffix(".m").unwrap_or(language);
+    if language.is_empty() || language == "www" || language.contains('.') {
+        return None;
+    }
+
+    let raw_title = match url.path().strip_prefix("/wiki/") {
+        Some(title) => title.to_string(),
+        None => url.query_pairs().find(|(key, _)| key == "title")?.1.into_owned(),
+    };
+    let title = percent_decode(&raw_title).replace(' ', "_");
+    if title.is_empty() {
+        return None;
+    }
+    Some((language.to_string(), title))
+}
+
+/// The Q-, P- or L-id of a Wikidata entity URL, in any of its usual spellings.
+fn wikidata_id(url: &Url, host: &str) -> Option<String> {
+    if !matches!(host, "wikidata.org" | "www.wikidata.org" | "m.wikidata.org") {
+        return None;
+    }
+    let last = url.path_segments()?.rfind(|s| !s.is_empty())?;
+    let id = last.strip_prefix("Property:").or_else(|| last.strip_prefix("Lexeme:")).unwrap_or(last);
+    let id = id.strip_suffix(".json").unwrap_or(id);
+    let is_id = id.len() > 1
+        && matches!(id.as_bytes()[0], b'Q' | b'P' | b'L')
+        && id[1..].chars().all(|c| c.is_ascii_digit());
+    is_id.then(|| id.to_string())
+}
+
+pub fn wikidata_url(id: &str) -> String {
+    if id.starts_with('P') {
+        format!("https://www.wikidata.org/wiki/Property:{}", id)
+    } else if id.starts_with('L') {
+        format!("https://www.wikidata.org/wiki/Lexeme:{}", id)
+    } else {
+        format!("https://www.wikidata.org/wiki/{}", id)
+    }
+}
+
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| text.to_string())
+}
+
+/// Re-encodes only the characters that would otherwise change the meaning of a `/wiki/` URL or
+/// end the scanner's URL match early, plus a final character `trim_url` would strip, so that
+/// canonical URLs are found again in full when they appear in text.
+fn encode_title(title: &str) -> String {
+    let mut encoded = String::with_capacity(title.len());
+    for c in title.chars() {
+        if c.is_whitespace() || matches!(c, '%' | '?' | '#' | '"' | '<' | '>' | '`' | '|' | '\\' | '^') {
+            push_percent_encoded(&mut encoded, c);
+        } else {
+            encoded.push(c);
+        }
+    }
+    while trim_url(&encoded).len() < encoded.len() {
+        let last = encoded.pop().unwrap();
+        push_percent_encoded(&mut encoded, last);
+    }
+    encoded
+}
+
+fn push_percent_encoded(encoded: &mut String, c: char) {
+    for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+        encoded.push_str(&format!("%{:02X}", byte));
+    }
+}
@@ -1,9 +1,54 @@
-use crate::data_structures::{WikidataEntity, WikidataFact};
+use crate::data_structures::{EntityCandidate, WikidataEntity, WikidataFact};
 use crate::cache::{save_entity_to_cache, load_entity_from_cache};
 use reqwest::Client;
 use serde_json::Value; // Keep serde_json::Value for manual parsing
 
-pub async fn fetch_wikidata_entity(client: &Client, wikipedia_title: Option<&str>, wikidata_id: Option<&str>) -> Result<Option<WikidataEntity>, Box<dyn std::error::Error>> {
+pub const WIKIDATA_API_URL: &str = "https://www.wikidata.org/w/api.php";
+
+/// Searches Wikidata items by label and alias with `wbsearchentities` and returns up to `limit`
+/// candidates. Label matches rank ahead of alias matches, which rank ahead of anything else;
+/// within each group the API's own order is kept. `api_url` is normally `WIKIDATA_API_URL`.
+pub async fn search_wikidata_entities(client: &Client, api_url: &str, term: &str, language: &str, limit: u32) -> Result<Vec<EntityCandidate>, Box<dyn std::error::Error>> {
+    let limit = limit.to_string();
+    let params = [
+        ("action", "wbsearchentities"),
+        ("format", "json"),
+        ("type", "item"),
+        ("search", term),
+        ("language", language),
+        ("uselang", language),
+        ("limit", limit.as_str()),
+    ];
+    let res = client.get(api_url).query(&params).send().await?.json::<Value>().await?;
+    if let Some(error) = res.get("error") {
+        return Err(format!("wbsearchentities failed: {}", error["info"].as_str().unwrap_or("unknown error")).into());
+    }
+
+    let mut candidates: Vec<EntityCandidate> = res["search"].as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|result| {
+            Some(EntityCandidate {
+                id: result["id"].as_str()?.to_string(),
+                label: result["label"].as_str().unwrap_or_default().to_string(),
+                description: result["description"].as_str().map(str::to_string),
+                match_type: result["match"]["type"].as_str().unwrap_or_default().to_string(),
+                match_text: result["match"]["text"].as_str().unwrap_or_default().to_string(),
+            })
+        })
+        .collect();
+    candidates.sort_by_key(|candidate| match candidate.match_type.as_str() {
+        "label" => 0,
+        "alias" => 1,
+        _ => 2,
+    });
+    Ok(candidates)
+}
+
+/// Fetches an entity by QID, or by the title of its article on `site`, a Wikidata site id such as
+/// `enwiki` or `dewiki`. `site` is ignored when looking up by QID.
+pub async fn fetch_wikidata_entity(client: &Client, site: &str, wikipedia_title: Option<&str>, wikidata_id: Option<&str>) -> Result<Option<WikidataEntity>, Box<dyn std::error::Error>> {
     let mut params = vec![
         ("action", "wbgetentities"),
         ("format", "json"),
@@ -11,7 +56,7 @@ pub async fn fetch_wikidata_entity(client: &Client, wikipedia_title: Option<&str
     ];
 
     if let Some(title) = wikipedia_title {
-        params.push(("sites", "enwiki"));
+        params.push(("sites", site));
         params.push(("titles", title));
     } else if let Some(id) = wikidata_id {
         params.push(("ids", id));
@@ -100,13 +145,12 @@ pub async fn fetch_wikidata_entity(client: &Client, wikipedia_title: Option<&str
     }))
 }
 
-pub async fn fetch_and_cache_wikidata_entity(client: &Client, wikipedia_title: Option<&str>, wikidata_id: Option<&str>) -> Result<Option<WikidataEntity>, Box<dyn std::error::Error>> {
+pub async fn fetch_and_cache_wikidata_entity(client: &Client, site: &str, wikipedia_title: Option<&str>, wikidata_id: Option<&str>) -> Result<Option<WikidataEntity>, Box<dyn std::error::Error>> {
     let query_id = if let Some(id) = wikidata_id {
         id.to_string()
     } else if let Some(title) = wikipedia_title {
-        // A simple way to generate a consistent ID for caching based on title
-        // In a real scenario, you might want to fetch the ID first and then use it
-        title.replace(" ", "_").to_string()
+        // Title lookups are cached under the site and title, as the QID isn't known up front
+        format!("{}_{}", site, title.replace(" ", "_"))
     } else {
         return Ok(None);
     };
@@ -118,12 +162,15 @@ pub async fn fetch_and_cache_wikidata_entity(client: &Client, wikipedia_title: O
     }
 
     println!("Fetching Wikidata entity from web: {}", query_id);
-    let entity = fetch_wikidata_entity(client, wikipedia_title, wikidata_id).await?;
+    let entity = fetch_wikidata_entity(client, site, wikipedia_title, wikidata_id).await?;
 
     if let Some(e) = &entity {
-        // Use the actual Wikidata ID for caching if available
-        save_entity_to_cache(e)?;
-        println!("Saved Wikidata entity to cache: {}", e.id);
+        // Saved under the QID too, so a later lookup by ID hits the same entry
+        save_entity_to_cache(&e.id, e)?;
+        if query_id != e.id {
+            save_entity_to_cache(&query_id, e)?;
+        }
+        println!("Saved Wikidata entity to cache: {}", query_id);
     }
 
     Ok(entity)
@@ -1,22 +1,130 @@
-use crate::data_structures::{WikipediaArticle, WikipediaLink};
+use crate::data_structures::{ArticleSection, InfoboxField, LinkKind, WikipediaArticle, WikipediaLink};
 use crate::cache::{save_article_to_cache, load_article_from_cache};
-use wikipedia::{Wikipedia}; // Import Wikipedia from the wikipedia crate
-use wikipedia::http::default::Client as WikipediaHttpClient;
+use crate::url_normalizer::percent_decode;
+use reqwest::Client;
+use scraper::{ElementRef, Node, Selector};
+use serde::Deserialize;
+use url::Url;
+
+/// Classes of elements left out of lead, section and infobox text: footnote markers, edit links,
+/// navigation boxes, the reference list, tables of contents and hidden metadata.
+const NOISE_CLASSES: &[&str] = &[
+    "reference", "mw-editsection", "navbox", "vertical-navbox", "reflist", "references",
+    "toc", "mw-references-wrap", "noprint", "metadata", "mw-empty-elt",
+];
+
+/// Namespaces, besides categories, files and templates, whose pages aren't articles. Titles
+/// like `Star Wars: Episode IV` contain a colon too, so only these prefixes count.
+const OTHER_NAMESPACES: &[&str] = &[
+    "Special", "Wikipedia", "Help", "Portal", "Talk", "User", "Module", "Draft", "MediaWiki",
+    "TimedText", "Book", "Gadget", "WP", "Project",
+];
+
+/// Interwiki prefixes that link to other Wikimedia projects from within a page title.
+const INTERWIKI_PREFIXES: &[&str] = &[
+    "wikt", "wiktionary", "commons", "c", "d", "wikidata", "s", "wikisource", "q", "wikiquote",
+    "b", "wikibooks", "n", "wikinews", "v", "wikiversity", "voy", "wikivoyage", "species",
+    "m", "meta", "mw", "metawikimedia", "foundation", "phab",
+];
+
+/// Hosts of the Wikimedia projects, which links to other wikis live on.
+const WIKIMEDIA_HOSTS: &[&str] = &[
+    "wikipedia.org", "wiktionary.org", "wikimedia.org", "wikidata.org", "wikiquote.org",
+    "wikisource.org", "wikibooks.org", "wikinews.org", "wikiversity.org", "wikivoyage.org",
+    "mediawiki.org", "wikimediafoundation.org",
+];
+
+/// Which parts of a page the cleaning pass in `extract_article_data_with` removes before the
+/// content text and links are collected. Everything but hatnotes is removed by default.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CleaningOptions {
+    /// Navigation boxes at the foot of the article and in the sidebar.
+    pub navboxes: bool,
+    /// The reference list and the `[1]` markers pointing into it.
+    pub references: bool,
+    /// The `[edit]` links next to headings.
+    pub edit_links: bool,
+    /// Notes such as "For other uses, see ..." above the lead or a section.
+    pub hatnotes: bool,
+    pub table_of_contents: bool,
+    /// Everything outside the article body: header, sidebar, footer and category bar.
+    pub page_chrome: bool,
+    /// Further CSS selectors to remove, e.g. `".sidebar"`.
+    pub selectors: Vec<String>,
+}
+
+impl Default for CleaningOptions {
+    fn default() -> Self {
+        CleaningOptions {
+            navboxes: true,
+            references: true,
+            edit_links: true,
+            hatnotes: false,
+            table_of_contents: true,
+            page_chrome: true,
+            selectors: Vec::new(),
+        }
+    }
+}
+
+impl CleaningOptions {
+    fn selectors(&self) -> Vec<String> {
+        let mut selectors: Vec<String> = [
+            (self.navboxes, ".navbox, .vertical-navbox"),
+            (self.references, ".reflist, .mw-references-wrap, ol.references, sup.reference"),
+            (self.edit_links, ".mw-editsection"),
+            (self.hatnotes, ".hatnote"),
+            (self.table_of_contents, "#toc, .toc, .mw-table-of-contents-container"),
+            (self.page_chrome, "#mw-navigation, #mw-panel, #mw-head, #footer, .mw-footer, #catlinks, .printfooter, .mw-jump-link"),
+        ].into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, selector)| selector.to_string())
+            .collect();
+        selectors.extend(self.selectors.iter().cloned());
+        selectors
+    }
+}
 
 pub fn extract_article_data(html_content: &str, url: &str) -> Option<WikipediaArticle> {
-    let document = scraper::Html::parse_document(html_content);
+    extract_article_data_with(html_content, url, &CleaningOptions::default())
+}
+
+/// Extracts an article, with its content text and links taken from the page after the parts
+/// selected by `options` are removed. The structured fields (lead, sections, infobox,
+/// categories, hatnotes and references) are read before cleaning, so they don't depend on it.
+pub fn extract_article_data_with(html_content: &str, url: &str, options: &CleaningOptions) -> Option<WikipediaArticle> {
+    let mut document = scraper::Html::parse_document(html_content);
     let title_selector = scraper::Selector::parse("h1#firstHeading").unwrap();
     let content_selector = scraper::Selector::parse("div#mw-content-text").unwrap();
     let link_selector = scraper::Selector::parse("a").unwrap();
 
     let title = document.select(&title_selector).next().map(|e| e.text().collect::<String>())?;
-    let content = document.select(&content_selector).next().map(|e| e.text().collect::<String>())?;
 
-    let links: Vec<WikipediaLink> = document.select(&link_selector)
+    let (lead, sections, hatnotes, infobox, references) = {
+        let body = document.select(&content_selector).next()?;
+        let body = body.select(&Selector::parse(".mw-parser-output").unwrap()).next().unwrap_or(body);
+        let (lead, sections, hatnotes) = extract_sections(body);
+        (lead, sections, hatnotes, extract_infobox(body), extract_references(body))
+    };
+    let categories = extract_categories(&document);
+
+    clean_document(&mut document, options);
+    let content_element = document.select(&content_selector).next()?;
+    let content = content_element.text().collect::<String>();
+
+    let base = Url::parse(url).ok();
+    let link_elements: Vec<ElementRef> = if options.page_chrome {
+        content_element.select(&link_selector).collect()
+    } else {
+        document.select(&link_selector).collect()
+    };
+    let links: Vec<WikipediaLink> = link_elements.into_iter()
         .filter_map(|element| {
             let href = element.value().attr("href")?.to_string();
             let text = element.text().collect::<String>();
-            Some(WikipediaLink { href, text })
+            let (kind, title, fragment) = classify_link(base.as_ref(), &href);
+            Some(WikipediaLink { href, text, kind, title, fragment, in_body: is_in_body(element) })
         })
         .collect();
 
@@ -26,37 +134,276 @@ pub fn extract_article_data(html_content: &str, url: &str) -> Option<WikipediaAr
         url: url.to_string(),
         links,
         revision_id: None, // We don't have revision ID from this method
+        lead,
+        sections,
+        infobox,
+        categories,
+        hatnotes,
+        references,
     })
 }
 
-pub async fn fetch_and_cache_wikipedia_article(url: &str, title: &str) -> Result<WikipediaArticle, Box<dyn std::error::Error>> {
+/// Detaches every element matching the selectors enabled in `options`. Invalid custom selectors
+/// are reported and skipped.
+fn clean_document(document: &mut scraper::Html, options: &CleaningOptions) {
+    let mut removed = Vec::new();
+    for selector in options.selectors() {
+        match Selector::parse(&selector) {
+            Ok(selector) => removed.extend(document.select(&selector).map(|element| element.id())),
+            Err(e) => eprintln!("Ignoring invalid cleaning selector '{}': {}", selector, e),
+        }
+    }
+    for id in removed {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+}
+
+/// Classifies `href` as found on the page at `base`, and resolves it to the linked page's title
+/// and fragment.
+fn classify_link(base: Option<&Url>, href: &str) -> (LinkKind, Option<String>, Option<String>) {
+    if let Some(fragment) = href.strip_prefix('#') {
+        return (LinkKind::Anchor, None, Some(percent_decode(fragment)));
+    }
+    let resolved = match base {
+        Some(base) => base.join(href),
+        None => Url::parse(href),
+    };
+    let Ok(resolved) = resolved else {
+        return (LinkKind::Other, None, None);
+    };
+    let fragment = resolved.fragment().filter(|f| !f.is_empty()).map(percent_decode);
+    let host = resolved.host_str().unwrap_or_default().to_lowercase();
+    let is_wikimedia = WIKIMEDIA_HOSTS.iter().any(|h| host == *h || host.ends_with(&format!(".{}", h)));
+    if !matches!(resolved.scheme(), "http" | "https") || !is_wikimedia {
+        return (LinkKind::External, None, None);
+    }
+
+    let title = match resolved.path().strip_prefix("/wiki/") {
+        Some(title) => Some(percent_decode(title)),
+        None if resolved.path() == "/w/index.php" => {
+            let title = resolved.query_pairs().find(|(key, _)| key == "title").map(|(_, title)| title.into_owned());
+            if resolved.query_pairs().any(|(key, _)| key == "action") {
+                return (LinkKind::Edit, title.map(|t| normalize_title(&t)), fragment);
+            }
+            title
+        },
+        None => None,
+    };
+    let title = title.map(|t| normalize_title(&t)).filter(|t| !t.is_empty());
+
+    let same_wiki = base.and_then(Url::host_str).map(str::to_lowercase).as_deref() == Some(host.as_str());
+    if !same_wiki {
+        return (LinkKind::Interwiki, title, fragment);
+    }
+    let Some(title) = title else {
+        return (LinkKind::Other, None, fragment);
+    };
+    let kind = match title.split_once(':').map(|(prefix, _)| prefix) {
+        Some("Category") => LinkKind::Category,
+        Some("File" | "Image" | "Media") => LinkKind::File,
+        Some("Template") => LinkKind::Template,
+        Some(prefix) if OTHER_NAMESPACES.contains(&prefix) || prefix.ends_with(" talk") => LinkKind::Other,
+        Some(prefix) if INTERWIKI_PREFIXES.contains(&prefix.to_lowercase().as_str()) => LinkKind::Interwiki,
+        _ => LinkKind::Article,
+    };
+    (kind, Some(title), fragment)
+}
+
+/// Turns a title from a URL into the form MediaWiki displays: spaces instead of underscores and
+/// a capital first letter.
+fn normalize_title(title: &str) -> String {
+    let title = title.replace('_', " ");
+    let title = title.trim();
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// A link is in the body if it's inside `#mw-content-text` but not inside a navbox.
+fn is_in_body(link: ElementRef) -> bool {
+    let mut in_content = false;
+    for ancestor in link.ancestors().filter_map(ElementRef::wrap) {
+        let element = ancestor.value();
+        if element.attr("role") == Some("navigation") || element.classes().any(|c| c == "navbox" || c == "vertical-navbox") {
+            return false;
+        }
+        if element.id() == Some("mw-content-text") {
+            in_content = true;
+        }
+    }
+    in_content
+}
+
+/// Walks the top-level blocks of the article body, splitting them into the lead paragraphs and
+/// one section per heading, and collecting hatnotes along the way.
+fn extract_sections(body: ElementRef) -> (String, Vec<ArticleSection>, Vec<String>) {
+    let mut lead = Vec::new();
+    let mut flat: Vec<(ArticleSection, Vec<String>)> = Vec::new();
+    let mut hatnotes = Vec::new();
+
+    for block in body.children().filter_map(ElementRef::wrap) {
+        if has_class(block, "hatnote") {
+            hatnotes.push(clean_text(block));
+            continue;
+        }
+        if has_class(block, "infobox") || is_noise(block) {
+            continue;
+        }
+        if let Some(section) = heading(block) {
+            flat.push((section, Vec::new()));
+            continue;
+        }
+        let text = clean_text(block);
+        if text.is_empty() {
+            continue;
+        }
+        match flat.last_mut() {
+            Some((_, paragraphs)) => paragraphs.push(text),
+            None if block.value().name() == "p" => lead.push(text),
+            None => {},
+        }
+    }
+
+    let mut sections: Vec<ArticleSection> = Vec::new();
+    for (mut section, paragraphs) in flat {
+        section.text = paragraphs.join("\n\n");
+        nest_section(&mut sections, section);
+    }
+    (lead.join("\n\n"), sections, hatnotes)
+}
+
+/// Appends `section` as a subsection of the last section with a lower level, or at the top.
+fn nest_section(sections: &mut Vec<ArticleSection>, section: ArticleSection) {
+    match sections.last_mut() {
+        Some(parent) if parent.level < section.level => nest_section(&mut parent.subsections, section),
+        _ => sections.push(section),
+    }
+}
+
+/// Recognizes both `<h2 id="...">` inside a `div.mw-heading` and the older
+/// `<h2><span class="mw-headline" id="...">` markup.
+fn heading(block: ElementRef) -> Option<ArticleSection> {
+    let element = if has_class(block, "mw-heading") {
+        block.children().filter_map(ElementRef::wrap).find(|e| heading_level(e).is_some())?
+    } else {
+        block
+    };
+    let level = heading_level(&element)?;
+    let headline = element.select(&Selector::parse(".mw-headline").unwrap()).next();
+    let anchor = headline.and_then(|h| h.value().id())
+        .or_else(|| element.value().id())
+        .unwrap_or_default()
+        .to_string();
+    Some(ArticleSection {
+        level,
+        heading: clean_text(headline.unwrap_or(element)),
+        anchor,
+        text: String::new(),
+        subsections: Vec::new(),
+    })
+}
+
+fn heading_level(element: &ElementRef) -> Option<u8> {
+    match element.value().name() {
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+/// Reads the label/value rows of the first infobox, skipping the title and image rows.
+fn extract_infobox(body: ElementRef) -> Vec<InfoboxField> {
+    let Some(infobox) = body.select(&Selector::parse("table.infobox").unwrap()).next() else {
+        return Vec::new();
+    };
+    let header_selector = Selector::parse("th").unwrap();
+    let data_selector = Selector::parse("td").unwrap();
+    infobox.select(&Selector::parse("tr").unwrap())
+        .filter_map(|row| {
+            let key = clean_text(row.select(&header_selector).next()?);
+            let value = clean_text(row.select(&data_selector).next()?);
+            (!key.is_empty() && !value.is_empty()).then_some(InfoboxField { key, value })
+        })
+        .collect()
+}
+
+/// The visible categories from the category bar; hidden maintenance categories are left out.
+fn extract_categories(document: &scraper::Html) -> Vec<String> {
+    let category_selector = Selector::parse("#mw-normal-catlinks li a").unwrap();
+    document.select(&category_selector)
+        .map(|link| link.text().collect::<String>().trim().to_string())
+        .filter(|category| !category.is_empty())
+        .collect()
+}
+
+fn extract_references(body: ElementRef) -> Vec<String> {
+    let text_selector = Selector::parse(".reference-text").unwrap();
+    body.select(&Selector::parse("ol.references > li").unwrap())
+        .map(|item| match item.select(&text_selector).next() {
+            Some(text) => clean_text(text),
+            None => clean_text(item),
+        })
+        .filter(|reference| !reference.is_empty())
+        .collect()
+}
+
+fn has_class(element: ElementRef, class: &str) -> bool {
+    element.value().classes().any(|c| c == class)
+}
+
+fn is_noise(element: ElementRef) -> bool {
+    matches!(element.value().name(), "style" | "script")
+        || element.value().classes().any(|c| NOISE_CLASSES.contains(&c))
+}
+
+/// The element's text without noise elements, with whitespace collapsed.
+fn clean_text(element: ElementRef) -> String {
+    fn collect(element: ElementRef, out: &mut String) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => out.push_str(text),
+                Node::Element(_) => {
+                    let child = ElementRef::wrap(child).unwrap();
+                    if !is_noise(child) {
+                        collect(child, out);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+    let mut text = String::new();
+    collect(element, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Fetches the article at `url`, caching it under `language` and the requested `title`, so
+/// articles of the same name in different languages, and redirected titles, are kept apart.
+pub async fn fetch_and_cache_wikipedia_article(client: &Client, url: &str, language: &str, title: &str, options: &CleaningOptions) -> Result<WikipediaArticle, Box<dyn std::error::Error>> {
+    let cache_key = format!("{}:{}", language, title);
     // Try to load from cache first
-    if let Ok(Some(article)) = load_article_from_cache(title) {
+    if let Ok(Some(article)) = load_article_from_cache(&cache_key) {
         println!("Loaded Wikipedia article from cache: {}", title);
         return Ok(article);
     }
 
     println!("Fetching Wikipedia article from web: {}", url);
 
-    let wiki = Wikipedia::new(WikipediaHttpClient::default()); // Use default client
-    let page = wiki.page_from_title(title.to_string()); // Convert &str to String
-
-    let page_content = page.get_content()?;
-    let page_url = url.to_string(); // Re-introducing this line
+    // extract_article_data needs the rendered HTML page, not the plain text the API returns
+    let page_content = client.get(url).send().await?.error_for_status()?.text().await?;
 
-    let article_data = extract_article_data(&page_content, url)
+    let article = extract_article_data_with(&page_content, url, options)
         .ok_or("Failed to extract article data from fetched content")?;
 
-    let article = WikipediaArticle {
-        title: article_data.title,
-        url: page_url,
-        revision_id: None, // The wikipedia crate doesn't directly expose revision ID in this way
-        content: article_data.content,
-        links: article_data.links,
-    };
-
     // Save to cache
-    save_article_to_cache(&article)?;
+    save_article_to_cache(&cache_key, &article)?;
     println!("Saved Wikipedia article to cache: {}", title);
 
     Ok(article)
@@ -0,0 +1,44 @@
+use wikidata_tool::crq_document::{build_crq_graph, parse_crq_document};
+use wikidata_tool::scan_cache::{ScanCache, DiffMode, FindingKind, Occurrence};
+
+const CRQ_059: &str = include_str!("../docs/CRQ-059-wikipedia-wikidata-extractor.md");
+
+#[test]
+fn test_parse_crq_document() {
+    let document = parse_crq_document("docs/CRQ-059-wikipedia-wikidata-extractor.md", CRQ_059).unwrap();
+    assert_eq!(document.id, "CRQ-059");
+    assert_eq!(document.title, "Implement Wikipedia and Wikidata Extractor");
+    assert!(document.problem.starts_with("To expand our knowledge base"));
+    assert!(document.proposed_solution.contains("**Create `wikipedia_extractor` Rust Crate:**"));
+    assert!(document.proposed_solution.contains("8.  **Tests (Extreme Programming Approach):**"));
+    assert!(document.justification.starts_with("This CRQ will significantly enhance"));
+    assert!(document.other_sections.is_empty());
+
+    assert!(parse_crq_document("docs/notes.md", "**Problem/Goal:**\nNo title line").is_none());
+}
+
+#[test]
+fn test_build_crq_graph_links_commits_and_files() {
+    let document = parse_crq_document("docs/CRQ-059-wikipedia-wikidata-extractor.md", CRQ_059).unwrap();
+    let mut scan_cache = ScanCache::new();
+    for (commit, path) in [("aaa", "docs/CRQ-059-wikipedia-wikidata-extractor.md"), ("bbb", "src/lib.rs"), ("bbb", "README.md")] {
+        scan_cache.record(FindingKind::CrqLink, "CRQ-059", Occurrence {
+            commit: commit.to_string(),
+            path: path.to_string(),
+            line: Some(1),
+            author: "Tester".to_string(),
+            timestamp: 0,
+            repository: None,
+            submodule: None,
+            diff_mode: DiffMode::Combined,
+            current_path: None,
+            copied_from: None,
+        });
+    }
+
+    let graph = build_crq_graph(vec![document], &scan_cache);
+    let trace = &graph["CRQ-059"];
+    assert!(trace.document.is_some());
+    assert_eq!(trace.commits.len(), 2);
+    assert_eq!(trace.commits["bbb"].files.iter().collect::<Vec<_>>(), vec!["README.md", "src/lib.rs"]);
+}
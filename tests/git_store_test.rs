@@ -0,0 +1,45 @@
+use git2::{Repository, Signature};
+use wikidata_tool::git_store::{load_scan_cache, save_scan_cache, write_commit_notes, read_commit_note, STATE_REF};
+use wikidata_tool::scan_cache::{ScanCache, DiffMode, FindingKind, Occurrence};
+
+#[test]
+fn test_scan_cache_and_notes_round_trip() {
+    let dir = std::env::temp_dir().join(format!("wikidata-tool-git-store-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let signature = Signature::now("Tester", "tester@example.com").unwrap();
+    let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+    let commit = repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[]).unwrap();
+
+    assert!(load_scan_cache(&repo).unwrap().is_none());
+
+    let mut scan_cache = ScanCache::new();
+    scan_cache.last_scanned_commit = Some(commit.to_string());
+    scan_cache.record(FindingKind::CrqLink, "CRQ-059", Occurrence {
+        commit: commit.to_string(),
+        path: "README.md".to_string(),
+        line: Some(1),
+        author: "Tester".to_string(),
+        timestamp: 0,
+        repository: None,
+        submodule: None,
+        diff_mode: DiffMode::Combined,
+        current_path: None,
+        copied_from: None,
+    });
+    let state = save_scan_cache(&repo, &scan_cache).unwrap();
+    // Saving unchanged state doesn't add another commit
+    assert_eq!(save_scan_cache(&repo, &scan_cache).unwrap(), state);
+    assert_eq!(repo.refname_to_id(STATE_REF).unwrap(), state);
+
+    let loaded = load_scan_cache(&repo).unwrap().unwrap();
+    assert_eq!(loaded.last_scanned_commit, scan_cache.last_scanned_commit);
+    assert_eq!(loaded.crq_links["CRQ-059"], scan_cache.crq_links["CRQ-059"]);
+
+    assert_eq!(write_commit_notes(&repo, &scan_cache).unwrap(), 1);
+    let note = read_commit_note(&repo, commit).unwrap().unwrap();
+    assert_eq!(note.added[0].value, "CRQ-059");
+    assert!(note.removed.is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,448 @@
+use git2::{IndexAddOption, Oid, Repository, Signature};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use wikidata_tool::history_scanner::{parse_date, resolve_tips, CommitSelection, HistoryScanner, ScanOptions, STAGED, UNSTAGED};
+use wikidata_tool::path_filter::{PathFilter, PathFilterConfig};
+use wikidata_tool::scan_cache::{DiffMode, FindingStatus, ScanCache};
+use wikidata_tool::term_extractor::TermExtractor;
+
+#[test]
+fn test_parse_date() {
+    assert_eq!(parse_date("1700000000", false).unwrap(), 1700000000);
+    assert_eq!(parse_date("1970-01-01", false).unwrap(), 0);
+    assert_eq!(parse_date("2024-03-01", false).unwrap(), 1709251200);
+    assert_eq!(parse_date("2024-03-01", true).unwrap(), 1709251200 + 86399);
+    assert!(parse_date("2024-13-01", false).is_err());
+    assert!(parse_date("yesterday", false).is_err());
+}
+
+fn init_repo(name: &str) -> (PathBuf, Repository) {
+    let dir = std::env::temp_dir().join(format!("wikidata-tool-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    (dir, repo)
+}
+
+fn write(dir: &Path, path: &str, content: &str) {
+    std::fs::write(dir.join(path), content).unwrap();
+}
+
+/// Stages the whole working tree, deletions included, and commits it with the given parents.
+/// `reference` (the current branch or detached HEAD for `HEAD`) is moved to the new commit even if that isn't a
+/// fast-forward, so amends can be written as a second commit on the old parent.
+fn commit(repo: &Repository, reference: &str, parents: &[Oid], message: &str) -> Oid {
+    commit_as(repo, reference, parents, message, "Tester")
+}
+
+/// `commit`, authored and committed by `author`.
+fn commit_as(repo: &Repository, reference: &str, parents: &[Oid], message: &str, author: &str) -> Oid {
+    let mut index = repo.index().unwrap();
+    index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+    index.update_all(["*"], None).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now(author, &format!("{}@example.com", author.to_lowercase())).unwrap();
+    let parents: Vec<_> = parents.iter().map(|oid| repo.find_commit(*oid).unwrap()).collect();
+    let parents: Vec<_> = parents.iter().collect();
+    let oid = repo.commit(None, &signature, &signature, message, &tree, &parents).unwrap();
+    let head = repo.find_reference("HEAD").unwrap();
+    match (reference, head.symbolic_target()) {
+        ("HEAD", Some(branch)) => repo.reference(branch, oid, true, message).map(drop),
+        // Submodule checkouts have a detached HEAD
+        ("HEAD", None) => repo.set_head_detached(oid),
+        (reference, _) => repo.reference(reference, oid, true, message).map(drop),
+    }.unwrap();
+    oid
+}
+
+fn scanner(diff_mode: DiffMode) -> HistoryScanner {
+    HistoryScanner::new(ScanOptions {
+        path_filter: PathFilter::new(&PathFilterConfig::default()).unwrap(),
+        term_extractor: TermExtractor::new(false),
+        diff_mode,
+    })
+}
+
+#[test]
+fn test_scan_uncommitted_with_deletions() {
+    let (dir, repo) = init_repo("uncommitted");
+    write(&dir, "a.md", "see CRQ-001\n");
+    write(&dir, "b.md", "see CRQ-002\n");
+    commit(&repo, "HEAD", &[], "initial");
+
+    // Staged: a.md deleted, c.md added. Unstaged: b.md deleted, d.md untracked.
+    std::fs::remove_file(dir.join("a.md")).unwrap();
+    write(&dir, "c.md", "see CRQ-003\n");
+    let mut index = repo.index().unwrap();
+    index.remove_path(Path::new("a.md")).unwrap();
+    index.add_path(Path::new("c.md")).unwrap();
+    index.write().unwrap();
+    std::fs::remove_file(dir.join("b.md")).unwrap();
+    write(&dir, "d.md", "see CRQ-004\n");
+
+    let mut scan_cache = ScanCache::new();
+    scanner(DiffMode::Combined).scan_uncommitted(&repo, &mut scan_cache).unwrap();
+
+    let labels: BTreeMap<&str, &str> = scan_cache.crq_links.iter()
+        .map(|(crq, finding)| (crq.as_str(), finding.occurrences[0].commit.as_str()))
+        .collect();
+    assert_eq!(labels, BTreeMap::from([("CRQ-003", STAGED), ("CRQ-004", UNSTAGED)]));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_rewrite_keeps_findings_of_unscanned_refs() {
+    let (dir, repo) = init_repo("rewrite");
+    write(&dir, "a.md", "start\n");
+    let base = commit(&repo, "HEAD", &[], "initial");
+    write(&dir, "b.md", "see CRQ-777\n");
+    commit(&repo, "refs/heads/feat2", &[base], "feature");
+    std::fs::remove_file(dir.join("b.md")).unwrap();
+    write(&dir, "a.md", "start\nsee CRQ-100\n");
+    commit(&repo, "HEAD", &[base], "main");
+
+    let all_refs = ["refs/heads/*".to_string()];
+    let mut scanner = scanner(DiffMode::Combined);
+    let mut scan_cache = ScanCache::new();
+    let mut watermarks = BTreeMap::new();
+    scanner.scan_repository(&repo, None, &resolve_tips(&repo, &all_refs).unwrap(), &mut watermarks, &mut scan_cache).unwrap();
+    assert!(scan_cache.crq_links.contains_key("CRQ-777"));
+
+    // Amend main, then rescan HEAD only: feat2 keeps its watermark, so its findings must stay
+    write(&dir, "a.md", "start\nsee CRQ-101\n");
+    commit(&repo, "HEAD", &[base], "main, amended");
+    scanner.scan_repository(&repo, None, &resolve_tips(&repo, &[]).unwrap(), &mut watermarks, &mut scan_cache).unwrap();
+    scanner.scan_repository(&repo, None, &resolve_tips(&repo, &all_refs).unwrap(), &mut watermarks, &mut scan_cache).unwrap();
+
+    let crqs: Vec<&str> = scan_cache.crq_links.iter()
+        .filter(|(_, finding)| !finding.occurrences.is_empty())
+        .map(|(crq, _)| crq.as_str())
+        .collect();
+    assert_eq!(crqs, vec!["CRQ-101", "CRQ-777"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_scan_finds_urls_with_apostrophes() {
+    let (dir, repo) = init_repo("apostrophe");
+    write(&dir, "a.md", "See https://en.wikipedia.org/wiki/Conway's_Game_of_Life.\n'https://en.wikipedia.org/wiki/Hilbert%27s_problems'\n");
+    commit(&repo, "HEAD", &[], "initial");
+
+    let mut scan_cache = ScanCache::new();
+    scanner(DiffMode::Combined).scan_range(&repo, None, &CommitSelection::default(), &mut scan_cache).unwrap();
+
+    let urls: Vec<&str> = scan_cache.urls.keys().map(String::as_str).collect();
+    assert_eq!(urls, vec!["https://en.wikipedia.org/wiki/Conway's_Game_of_Life", "https://en.wikipedia.org/wiki/Hilbert's_problems"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_scan_tracks_deleted_lines() {
+    let (dir, repo) = init_repo("deletion");
+    write(&dir, "a.md", "see https://a.org/ first\n");
+    write(&dir, "b.md", "see CRQ-001\n");
+    let first = commit(&repo, "HEAD", &[], "initial");
+    write(&dir, "a.md", "see https://a.org/ again\n");
+    let second = commit(&repo, "HEAD", &[first], "edit a.md");
+    std::fs::remove_file(dir.join("b.md")).unwrap();
+    let third = commit(&repo, "HEAD", &[second], "delete b.md");
+
+    let mut scan_cache = ScanCache::new();
+    scanner(DiffMode::Combined).scan_range(&repo, None, &CommitSelection::default(), &mut scan_cache).unwrap();
+
+    let url = &scan_cache.urls["https://a.org/"];
+    assert_eq!(url.status, FindingStatus::Live);
+    assert_eq!(url.live_count, 1);
+    assert_eq!(url.counts_by_file.get("a.md"), Some(&1));
+    let crq = &scan_cache.crq_links["CRQ-001"];
+    assert_eq!(crq.status, FindingStatus::Removed);
+    assert_eq!(crq.removed_in_commit, Some(third.to_string()));
+    assert_eq!(crq.removals[0].path, "b.md");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Builds `main: base - ours - merge` where the merge also brings in `feat: base - theirs`, and
+/// resolves with a line of its own. Returns (ours, theirs, merge).
+fn merge_history(dir: &Path, repo: &Repository) -> (Oid, Oid, Oid) {
+    write(dir, "a.md", "start\n");
+    let base = commit(repo, "HEAD", &[], "initial");
+    write(dir, "a.md", "start\nsee CRQ-010\n");
+    let ours = commit(repo, "HEAD", &[base], "ours");
+    write(dir, "a.md", "start\n");
+    write(dir, "b.md", "see CRQ-020\n");
+    let theirs = commit(repo, "refs/heads/feat", &[base], "theirs");
+    write(dir, "a.md", "start\nsee CRQ-010\n");
+    write(dir, "c.md", "see CRQ-030\n");
+    let merge = commit(repo, "HEAD", &[ours, theirs], "merge feat");
+    (ours, theirs, merge)
+}
+
+/// Scans HEAD in `diff_mode` and returns the commit each CRQ was first seen in.
+fn scan_crq_commits(repo: &Repository, diff_mode: DiffMode) -> BTreeMap<String, String> {
+    let mut scan_cache = ScanCache::new();
+    let tips = resolve_tips(repo, &[]).unwrap();
+    scanner(diff_mode).scan_repository(repo, None, &tips, &mut BTreeMap::new(), &mut scan_cache).unwrap();
+    scan_cache.crq_links.into_iter().map(|(crq, finding)| (crq, finding.first_seen_commit)).collect()
+}
+
+#[test]
+fn test_merge_attribution_by_diff_mode() {
+    let (dir, repo) = init_repo("merge");
+    let (ours, theirs, merge) = merge_history(&dir, &repo);
+    let expected = |entries: &[(&str, Oid)]| -> BTreeMap<String, String> {
+        entries.iter().map(|(crq, commit)| (crq.to_string(), commit.to_string())).collect()
+    };
+
+    // Combined credits the branch commit; the merge only gets what it wrote itself
+    assert_eq!(scan_crq_commits(&repo, DiffMode::Combined), expected(&[("CRQ-010", ours), ("CRQ-020", theirs), ("CRQ-030", merge)]));
+    // First-parent never visits the branch, so the merge is credited with everything it brought in
+    assert_eq!(scan_crq_commits(&repo, DiffMode::FirstParent), expected(&[("CRQ-010", ours), ("CRQ-020", merge), ("CRQ-030", merge)]));
+    // Skipping merges loses the resolution line
+    assert_eq!(scan_crq_commits(&repo, DiffMode::SkipMerges), expected(&[("CRQ-010", ours), ("CRQ-020", theirs)]));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_rename_carries_provenance() {
+    let (dir, repo) = init_repo("rename");
+    let content = "# Plan\n\nsee CRQ-040\n\nFirst step.\nSecond step.\nThird step.\n";
+    write(&dir, "old.md", content);
+    let first = commit(&repo, "HEAD", &[], "initial");
+    std::fs::remove_file(dir.join("old.md")).unwrap();
+    write(&dir, "new.md", &format!("{}Fourth step.\n", content));
+    commit(&repo, "HEAD", &[first], "rename old.md to new.md");
+
+    let mut scan_cache = ScanCache::new();
+    scanner(DiffMode::Combined).scan_range(&repo, None, &CommitSelection::default(), &mut scan_cache).unwrap();
+
+    let crq = &scan_cache.crq_links["CRQ-040"];
+    assert_eq!(crq.occurrences.len(), 1);
+    assert_eq!(crq.occurrences[0].commit, first.to_string());
+    assert_eq!(crq.occurrences[0].path, "old.md");
+    assert_eq!(crq.occurrences[0].latest_path(), "new.md");
+    assert_eq!(crq.counts_by_file, BTreeMap::from([("new.md".to_string(), 1)]));
+    assert_eq!(crq.status, FindingStatus::Live);
+    assert!(crq.removals.is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_copy_keeps_findings_after_source_is_deleted() {
+    let (dir, repo) = init_repo("copy");
+    let content = "# Notes\n\nsee CRQ-123\nand https://example.org/x\n\nFirst step.\nSecond step.\nThird step.\n";
+    write(&dir, "a.md", content);
+    let first = commit(&repo, "HEAD", &[], "initial");
+    // Git only considers modified files as copy sources
+    write(&dir, "a.md", &format!("{}Fourth step.\n", content));
+    write(&dir, "b.md", content);
+    let copy = commit(&repo, "HEAD", &[first], "copy a.md to b.md");
+    std::fs::remove_file(dir.join("a.md")).unwrap();
+    let delete = commit(&repo, "HEAD", &[copy], "remove a.md");
+
+    let mut scan_cache = ScanCache::new();
+    scanner(DiffMode::Combined).scan_range(&repo, None, &CommitSelection::default(), &mut scan_cache).unwrap();
+
+    for finding in [&scan_cache.crq_links["CRQ-123"], &scan_cache.urls["https://example.org/x"]] {
+        assert_eq!(finding.status, FindingStatus::Live, "{}", finding.value);
+        assert_eq!(finding.live_count, 1);
+        assert_eq!(finding.counts_by_file, BTreeMap::from([("b.md".to_string(), 1)]));
+        assert_eq!(finding.occurrences.len(), 2);
+        let copied = &finding.occurrences[1];
+        assert_eq!(copied.commit, copy.to_string());
+        assert_eq!(copied.path, "b.md");
+        assert_eq!(copied.copied_from.as_deref(), Some("a.md"));
+        assert_eq!(finding.removals.len(), 1);
+        assert_eq!(finding.removals[0].commit, delete.to_string());
+        assert_eq!(finding.removals[0].path, "a.md");
+    }
+    assert!(scan_cache.live_paths(None).contains("b.md"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Adds the repository at `url` as a submodule of `repo` at `path`, cloned and staged.
+fn add_submodule(repo: &Repository, url: &Path, path: &str) {
+    let mut submodule = repo.submodule(url.to_str().unwrap(), Path::new(path), true).unwrap();
+    submodule.clone(None).unwrap();
+    submodule.add_finalize().unwrap();
+}
+
+#[test]
+fn test_scan_nested_submodules() {
+    let dir = std::env::temp_dir().join(format!("wikidata-tool-submodules-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let deep_source = Repository::init(dir.join("deep")).unwrap();
+    write(&dir.join("deep"), "deep.md", "see CRQ-303\n");
+    commit(&deep_source, "HEAD", &[], "deep");
+    let inner_source = Repository::init(dir.join("inner")).unwrap();
+    write(&dir.join("inner"), "inner.md", "see CRQ-302\n");
+    add_submodule(&inner_source, &dir.join("deep"), "vendor/deep");
+    commit(&inner_source, "HEAD", &[], "inner");
+
+    let top_dir = dir.join("top");
+    let top = Repository::init(&top_dir).unwrap();
+    write(&top_dir, "top.md", "see CRQ-301\n");
+    add_submodule(&top, &dir.join("inner"), "libs/inner");
+    commit(&top, "HEAD", &[], "top");
+    let inner = top.find_submodule("libs/inner").unwrap().open().unwrap();
+    inner.find_submodule("vendor/deep").unwrap().update(true, None).unwrap();
+    let deep = Repository::open(top_dir.join("libs/inner/vendor/deep")).unwrap();
+
+    let mut scanner = scanner(DiffMode::Combined);
+    let mut scan_cache = ScanCache::new();
+    let mut watermarks = BTreeMap::new();
+    scanner.scan_repository(&top, None, &resolve_tips(&top, &[]).unwrap(), &mut watermarks, &mut scan_cache).unwrap();
+    scanner.scan_submodules(&top, "", &mut scan_cache).unwrap();
+
+    let occurrence = |crq: &str| scan_cache.crq_links[crq].occurrences.clone();
+    assert_eq!(occurrence("CRQ-301")[0].submodule, None);
+    assert_eq!(occurrence("CRQ-302")[0].submodule.as_deref(), Some("libs/inner"));
+    assert_eq!(occurrence("CRQ-303")[0].submodule.as_deref(), Some("libs/inner/vendor/deep"));
+    assert_eq!(occurrence("CRQ-303")[0].full_path(), "libs/inner/vendor/deep/deep.md");
+    let head = |repo: &Repository| repo.head().unwrap().peel_to_commit().unwrap().id().to_string();
+    assert_eq!(scan_cache.submodule_watermarks, BTreeMap::from([
+        ("libs/inner".to_string(), head(&inner)),
+        ("libs/inner/vendor/deep".to_string(), head(&deep)),
+    ]));
+
+    // Only the new commit in the nested submodule is scanned the second time
+    write(&top_dir.join("libs/inner/vendor/deep"), "deep.md", "see CRQ-303\nand CRQ-304\n");
+    let parent = deep.head().unwrap().peel_to_commit().unwrap().id();
+    let second = commit(&deep, "HEAD", &[parent], "deep again");
+    scanner.scan_submodules(&top, "", &mut scan_cache).unwrap();
+
+    assert_eq!(scan_cache.crq_links["CRQ-302"].occurrences.len(), 1);
+    assert_eq!(scan_cache.crq_links["CRQ-303"].occurrences.len(), 1);
+    let added = &scan_cache.crq_links["CRQ-304"].occurrences;
+    assert_eq!(added.len(), 1);
+    assert_eq!(added[0].commit, second.to_string());
+    assert_eq!(added[0].submodule.as_deref(), Some("libs/inner/vendor/deep"));
+    assert_eq!(scan_cache.submodule_watermarks["libs/inner/vendor/deep"], second.to_string());
+    assert_eq!(scan_cache.submodule_watermarks["libs/inner"], head(&inner));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_scan_branches_sharing_history() {
+    let (dir, repo) = init_repo("branches");
+    let main = repo.find_reference("HEAD").unwrap().symbolic_target().unwrap().to_string();
+    write(&dir, "a.md", "see CRQ-501\n");
+    let first = commit(&repo, "HEAD", &[], "first");
+    write(&dir, "b.md", "see CRQ-502\n");
+    let shared = commit(&repo, "HEAD", &[first], "shared");
+    write(&dir, "c.md", "see CRQ-503\n");
+    let feature = commit(&repo, "refs/heads/feature", &[shared], "feature");
+
+    let mut scanner = scanner(DiffMode::Combined);
+    let mut scan_cache = ScanCache::new();
+    let mut watermarks = BTreeMap::new();
+    let ref_globs = ["refs/heads/*".to_string()];
+    scanner.scan_repository(&repo, None, &resolve_tips(&repo, &ref_globs).unwrap(), &mut watermarks, &mut scan_cache).unwrap();
+
+    for (crq, commit) in [("CRQ-501", first), ("CRQ-502", shared), ("CRQ-503", feature)] {
+        let occurrences = &scan_cache.crq_links[crq].occurrences;
+        assert_eq!(occurrences.len(), 1, "{}", crq);
+        assert_eq!(occurrences[0].commit, commit.to_string());
+    }
+    assert_eq!(watermarks, BTreeMap::from([
+        ("HEAD".to_string(), shared.to_string()),
+        (main.clone(), shared.to_string()),
+        ("refs/heads/feature".to_string(), feature.to_string()),
+    ]));
+
+    // Each ref resumes from its own watermark; c.md only exists on the feature branch
+    std::fs::remove_file(dir.join("c.md")).unwrap();
+    write(&dir, "a.md", "see CRQ-501\nand CRQ-504\n");
+    let main_tip = commit(&repo, "HEAD", &[shared], "main again");
+    scanner.scan_repository(&repo, None, &resolve_tips(&repo, &ref_globs).unwrap(), &mut watermarks, &mut scan_cache).unwrap();
+
+    assert_eq!(scan_cache.crq_links["CRQ-501"].occurrences.len(), 1);
+    assert_eq!(scan_cache.crq_links["CRQ-503"].occurrences.len(), 1);
+    assert_eq!(scan_cache.crq_links["CRQ-504"].occurrences[0].commit, main_tip.to_string());
+    assert_eq!(watermarks[&main], main_tip.to_string());
+    assert_eq!(watermarks["refs/heads/feature"], feature.to_string());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_memoized_blobs_scan_the_same_in_parallel() {
+    let (dir, repo) = init_repo("memoized");
+    // One blob, committed as Markdown, as Rust, and again on another branch
+    let content = "// see CRQ-601\nfn wiki_item() {}\n";
+    write(&dir, "notes.md", content);
+    let first = commit(&repo, "HEAD", &[], "notes");
+    write(&dir, "lib.rs", content);
+    let second = commit(&repo, "HEAD", &[first], "same content as Rust");
+    std::fs::remove_file(dir.join("lib.rs")).unwrap();
+    write(&dir, "copy.md", content);
+    let feature = commit(&repo, "refs/heads/feature", &[first], "same content on a branch");
+
+    // As with `--jobs`, everything runs in a pool of the given size
+    let scan = |jobs: usize| {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build().unwrap();
+        pool.install(|| {
+            let repo = Repository::open(&dir).unwrap();
+            let mut scan_cache = ScanCache::new();
+            let mut watermarks = BTreeMap::new();
+            let tips = resolve_tips(&repo, &["refs/heads/*".to_string()]).unwrap();
+            scanner(DiffMode::Combined).scan_repository(&repo, None, &tips, &mut watermarks, &mut scan_cache).unwrap();
+            scan_cache
+        })
+    };
+    let serial = scan(1);
+    let parallel = scan(4);
+    assert_eq!(serde_json::to_value(&serial).unwrap(), serde_json::to_value(&parallel).unwrap());
+
+    let mut crq: Vec<(String, String)> = serial.crq_links["CRQ-601"].occurrences.iter()
+        .map(|o| (o.commit.clone(), o.path.clone()))
+        .collect();
+    crq.sort();
+    let mut expected = vec![
+        (first.to_string(), "notes.md".to_string()),
+        (second.to_string(), "lib.rs".to_string()),
+        (feature.to_string(), "copy.md".to_string()),
+    ];
+    expected.sort();
+    assert_eq!(crq, expected);
+    // Only the Rust reading of the blob yields item names and comments
+    let item = &serial.item_names["wiki_item"].occurrences;
+    assert_eq!((item.len(), item[0].path.as_str()), (1, "lib.rs"));
+    assert_eq!(serial.line_comments["see CRQ-601"].occurrences.len(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_blame_credits_each_author_across_a_rename() {
+    let (dir, repo) = init_repo("blame");
+    write(&dir, "notes.md", "# Notes\n\nsee CRQ-701\n");
+    let alice = commit_as(&repo, "HEAD", &[], "notes", "Alice");
+    write(&dir, "notes.md", "# Notes\n\nsee CRQ-701\nand CRQ-702\n");
+    let bob = commit_as(&repo, "HEAD", &[alice], "more notes", "Bob");
+    std::fs::rename(dir.join("notes.md"), dir.join("plan.md")).unwrap();
+    commit_as(&repo, "HEAD", &[bob], "rename notes.md to plan.md", "Carol");
+
+    let mut scanner = scanner(DiffMode::Combined);
+    let mut scan_cache = ScanCache::new();
+    scanner.scan_range(&repo, None, &CommitSelection::default(), &mut scan_cache).unwrap();
+    scanner.blame_head(&repo, &mut scan_cache).unwrap();
+
+    for (crq, line, commit, author) in [("CRQ-701", 3, alice, "Alice"), ("CRQ-702", 4, bob, "Bob")] {
+        let blame = &scan_cache.crq_links[crq].blame;
+        assert_eq!(blame.len(), 1, "{}", crq);
+        assert_eq!(blame[0].path, "plan.md");
+        assert_eq!(blame[0].line, Some(line));
+        assert_eq!(blame[0].commit, commit.to_string());
+        assert_eq!(blame[0].author, author);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
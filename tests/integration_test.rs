@@ -30,7 +30,7 @@ use wikipedia_extractor::wikidata_client::{fetch_wikidata_entity, fetch_and_cach
         .user_agent("MyRustWikipediaExtractor/1.0 (contact@example.com)")
         .build().unwrap();
     
-    let entity = fetch_and_cache_wikidata_entity(&client, Some("Rust (programming language)"), None).await?; // Use ? operator
+    let entity = fetch_and_cache_wikidata_entity(&client, "enwiki", Some("Rust (programming language)"), None).await?; // Use ? operator
     assert!(entity.is_some(), "Failed to fetch Wikidata entity");
     
     let entity = entity.unwrap();
@@ -50,7 +50,7 @@ use wikipedia_extractor::wikidata_client::{fetch_wikidata_entity, fetch_and_cach
         .user_agent("MyRustWikipediaExtractor/1.0 (contact@example.com)")
         .build().unwrap();
     
-    let entity = fetch_and_cache_wikidata_entity(&client, None, Some("Q768046")).await?; // Use ? operator
+    let entity = fetch_and_cache_wikidata_entity(&client, "enwiki", None, Some("Q768046")).await?; // Use ? operator
     assert!(entity.is_some(), "Failed to fetch Wikidata entity");
     
     let entity = entity.unwrap();
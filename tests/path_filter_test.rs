@@ -0,0 +1,24 @@
+use wikidata_tool::path_filter::{PathFilter, PathFilterConfig};
+
+#[test]
+fn test_default_excludes_lockfiles() {
+    let path_filter = PathFilter::new(&PathFilterConfig::default()).unwrap();
+    assert!(!path_filter.is_included("Cargo.lock"));
+    assert!(!path_filter.is_included("other/wiki_data_extractor/Cargo.lock"));
+    assert!(path_filter.is_included("src/main.rs"));
+}
+
+#[test]
+fn test_include_and_exclude_globs() {
+    let config = PathFilterConfig {
+        include: vec!["src/**".to_string(), "docs/*.md".to_string()],
+        exclude: vec!["src/generated/**".to_string()],
+        max_blob_size: None,
+    };
+    let path_filter = PathFilter::new(&config).unwrap();
+    assert!(path_filter.is_included("src/main.rs"));
+    assert!(path_filter.is_included("docs/future_plan.md"));
+    assert!(!path_filter.is_included("docs/nested/plan.md"));
+    assert!(!path_filter.is_included("src/generated/templates.rs"));
+    assert!(!path_filter.is_included("cache/wikidata/Q768046.json"));
+}
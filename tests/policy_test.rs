@@ -0,0 +1,110 @@
+use git2::{Index, Repository, Signature};
+use std::collections::BTreeSet;
+use std::path::Path;
+use wikidata_tool::history_scanner::ScanOptions;
+use wikidata_tool::path_filter::{PathFilter, PathFilterConfig};
+use wikidata_tool::policy::{check_commit_message, check_staged, known_crqs, use_hook_index, PolicyConfig};
+use wikidata_tool::scan_cache::DiffMode;
+use wikidata_tool::term_extractor::TermExtractor;
+
+#[test]
+fn test_commit_message_must_reference_known_crq() {
+    let config = PolicyConfig::default();
+    let known: BTreeSet<String> = ["CRQ-059".to_string()].into_iter().collect();
+
+    assert!(check_commit_message("Add extractor for CRQ-059\n", &config, &known).is_empty());
+    assert_eq!(check_commit_message("Add extractor\n# CRQ-059 in a comment\n", &config, &known).len(), 1);
+    let unknown = check_commit_message("Add extractor for CRQ-999\n", &config, &known);
+    assert_eq!(unknown[0].message, "CRQ-999 has no document in docs/");
+    assert!(check_commit_message("Merge branch 'main'\n", &config, &known).is_empty());
+
+    let relaxed = PolicyConfig { require_crq_reference: false, ..PolicyConfig::default() };
+    assert!(check_commit_message("Add extractor\n", &relaxed, &known).is_empty());
+}
+
+/// Stages `content` as `a.md` in a fresh repository with one empty commit and checks it.
+fn check_staged_content(name: &str, content: &str) -> Vec<String> {
+    let dir = std::env::temp_dir().join(format!("wikidata-tool-policy-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let signature = Signature::now("Tester", "tester@example.com").unwrap();
+    let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[]).unwrap();
+
+    std::fs::write(dir.join("a.md"), content).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("a.md")).unwrap();
+    index.write().unwrap();
+
+    let options = ScanOptions {
+        path_filter: PathFilter::new(&PathFilterConfig::default()).unwrap(),
+        term_extractor: TermExtractor::new(false),
+        diff_mode: DiffMode::Combined,
+    };
+    let violations = check_staged(&repo, &options, &PolicyConfig::default()).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    violations.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn test_staged_wikipedia_urls_must_be_canonical() {
+    let violations = check_staged_content("encoded", "intro\nSee https://en.m.wikipedia.org/wiki/Hilbert%27s_problems.\n");
+    assert_eq!(violations, vec![
+        "a.md:2: Wikipedia URL https://en.m.wikipedia.org/wiki/Hilbert%27s_problems is not canonical; use https://en.wikipedia.org/wiki/Hilbert's_problems",
+    ]);
+
+    // The suggested forms are accepted as written, apostrophes and trailing encodings included
+    let canonical = "See https://en.wikipedia.org/wiki/Hilbert's_problems.\n(https://en.wikipedia.org/wiki/Washington,_D.C%2E)\n";
+    assert!(check_staged_content("canonical", canonical).is_empty());
+}
+
+#[test]
+fn test_staged_wikipedia_section_links_keep_their_fragment() {
+    assert!(check_staged_content("section", "See https://en.wikipedia.org/wiki/Alan_Turing#Early_life\n").is_empty());
+
+    let violations = check_staged_content("mobile-section", "See https://en.m.wikipedia.org/wiki/Alan_Turing#Early_life\n");
+    assert_eq!(violations, vec![
+        "a.md:1: Wikipedia URL https://en.m.wikipedia.org/wiki/Alan_Turing#Early_life is not canonical; use https://en.wikipedia.org/wiki/Alan_Turing#Early_life",
+    ]);
+}
+
+#[test]
+fn test_hooks_read_the_index_git_points_them_at() {
+    let dir = std::env::temp_dir().join(format!("wikidata-tool-policy-index-file-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let signature = Signature::now("Tester", "tester@example.com").unwrap();
+    let tree = repo.find_tree(repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[]).unwrap();
+
+    // Stage into a separate index file, as `git commit -a` does, leaving .git/index untouched
+    std::fs::write(dir.join("a.md"), "See https://en.m.wikipedia.org/wiki/Alan_Turing\n").unwrap();
+    std::fs::create_dir_all(dir.join("docs")).unwrap();
+    std::fs::write(dir.join("docs/CRQ-007-plan.md"), "# CRQ-007\n").unwrap();
+    let index_file = dir.join(".git/next-index.lock");
+    let mut hook_index = Index::open(&index_file).unwrap();
+    repo.set_index(&mut hook_index).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("a.md")).unwrap();
+    index.add_path(Path::new("docs/CRQ-007-plan.md")).unwrap();
+    index.write().unwrap();
+
+    let options = ScanOptions {
+        path_filter: PathFilter::new(&PathFilterConfig::default()).unwrap(),
+        term_extractor: TermExtractor::new(false),
+        diff_mode: DiffMode::Combined,
+    };
+    let config = PolicyConfig::default();
+    let repo = Repository::open(&dir).unwrap();
+    assert!(check_staged(&repo, &options, &config).unwrap().is_empty());
+    assert!(known_crqs(&repo, "docs").unwrap().is_empty());
+
+    std::env::set_var("GIT_INDEX_FILE", &index_file);
+    let result = use_hook_index(&repo);
+    std::env::remove_var("GIT_INDEX_FILE");
+    result.unwrap();
+    assert_eq!(check_staged(&repo, &options, &config).unwrap().len(), 1);
+    assert_eq!(known_crqs(&repo, "docs").unwrap(), BTreeSet::from(["CRQ-007".to_string()]));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
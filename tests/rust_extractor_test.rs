@@ -0,0 +1,41 @@
+use wikidata_tool::rust_extractor::{extract_rust_fragments, RustFragment, RustFragmentKind};
+
+const SOURCE: &str = r#"//! Wikidata client.
+
+/// Fetches an entity from Wikidata.
+pub fn fetch_entity(id: &str) -> String {
+    // See https://www.wikidata.org/wiki/Special:EntityData
+    let url = format!("https://www.wikidata.org/wiki/{}", id); /* not a "string" */
+    let quote = '"';
+    url
+}
+
+struct WikidataEntity;
+"#;
+
+fn fragment(kind: RustFragmentKind, text: &str, line: u32) -> RustFragment {
+    RustFragment { kind, text: text.to_string(), line }
+}
+
+#[test]
+fn test_extract_rust_fragments() {
+    let fragments = extract_rust_fragments(SOURCE).unwrap();
+    let expected = [
+        fragment(RustFragmentKind::DocComment, "Wikidata client.", 1),
+        fragment(RustFragmentKind::DocComment, "Fetches an entity from Wikidata.", 3),
+        fragment(RustFragmentKind::ItemName, "fetch_entity", 4),
+        fragment(RustFragmentKind::LineComment, "See https://www.wikidata.org/wiki/Special:EntityData", 5),
+        fragment(RustFragmentKind::StringLiteral, "https://www.wikidata.org/wiki/{}", 6),
+        fragment(RustFragmentKind::LineComment, "not a \"string\"", 6),
+        fragment(RustFragmentKind::ItemName, "WikidataEntity", 11),
+    ];
+    for fragment in &expected {
+        assert!(fragments.contains(fragment), "missing {:?} in {:?}", fragment, fragments);
+    }
+    assert_eq!(fragments.len(), expected.len());
+}
+
+#[test]
+fn test_unparseable_source_returns_none() {
+    assert!(extract_rust_fragments("fn broken( {").is_none());
+}
@@ -0,0 +1,147 @@
+use wikidata_tool::scan_cache::{ScanCache, DiffMode, FindingKind, FindingStatus, Occurrence};
+
+fn occurrence(commit: &str, path: &str, line: u32) -> Occurrence {
+    Occurrence {
+        commit: commit.to_string(),
+        path: path.to_string(),
+        line: Some(line),
+        author: "Tester".to_string(),
+        timestamp: 0,
+        repository: None,
+        submodule: None,
+        diff_mode: DiffMode::Combined,
+        current_path: None,
+        copied_from: None,
+    }
+}
+
+#[test]
+fn test_record_tracks_first_and_last_seen_commits() {
+    let mut scan_cache = ScanCache::new();
+    scan_cache.record(FindingKind::Url, "https://www.wikidata.org", occurrence("aaa", "README.md", 3));
+    scan_cache.record(FindingKind::Url, "https://www.wikidata.org", occurrence("bbb", "docs/plan.md", 10));
+
+    let finding = &scan_cache.urls["https://www.wikidata.org"];
+    assert_eq!(finding.first_seen_commit, "aaa");
+    assert_eq!(finding.last_seen_commit, "bbb");
+    assert_eq!(finding.occurrences.len(), 2);
+    assert_eq!(finding.occurrences[1].path, "docs/plan.md");
+}
+
+#[test]
+fn test_query_matches_all_kinds() {
+    let mut scan_cache = ScanCache::new();
+    scan_cache.record(FindingKind::CrqLink, "CRQ-059", occurrence("aaa", "docs/CRQ-059.md", 1));
+    scan_cache.record(FindingKind::Term, "wikidata", occurrence("aaa", "src/lib.rs", 2));
+
+    assert_eq!(scan_cache.query("CRQ-059").len(), 1);
+    assert_eq!(scan_cache.query("wikidata")[0].kind, FindingKind::Term);
+    assert!(scan_cache.query("missing").is_empty());
+}
+
+#[test]
+fn test_retain_occurrences_drops_abandoned_commits() {
+    let mut scan_cache = ScanCache::new();
+    scan_cache.record(FindingKind::Url, "https://a.org", occurrence("aaa", "a.md", 1));
+    scan_cache.record(FindingKind::Url, "https://a.org", occurrence("bbb", "b.md", 1));
+    scan_cache.record(FindingKind::Url, "https://b.org", occurrence("bbb", "b.md", 2));
+
+    scan_cache.retain_occurrences(None, None, |o| o.commit == "aaa");
+
+    assert_eq!(scan_cache.urls["https://a.org"].last_seen_commit, "aaa");
+    assert!(!scan_cache.urls.contains_key("https://b.org"));
+}
+
+#[test]
+fn test_record_rename_moves_counts_and_provenance() {
+    let mut scan_cache = ScanCache::new();
+    scan_cache.record(FindingKind::Term, "wikidata", occurrence("aaa", "src/old.rs", 1));
+    scan_cache.record_rename("src/old.rs", "src/new.rs");
+
+    let finding = &scan_cache.terms["wikidata"];
+    assert_eq!(finding.counts_by_file.get("src/new.rs"), Some(&1));
+    assert!(!finding.counts_by_file.contains_key("src/old.rs"));
+    assert_eq!(finding.occurrences[0].path, "src/old.rs");
+    assert_eq!(finding.occurrences[0].latest_path(), "src/new.rs");
+}
+
+#[test]
+fn test_record_removal_tracks_status() {
+    let mut scan_cache = ScanCache::new();
+    scan_cache.record(FindingKind::CrqLink, "CRQ-001", occurrence("aaa", "a.md", 1));
+    scan_cache.record(FindingKind::CrqLink, "CRQ-001", occurrence("bbb", "b.md", 1));
+    scan_cache.record_removal(FindingKind::CrqLink, "CRQ-001", occurrence("ccc", "a.md", 1));
+    assert_eq!(scan_cache.crq_links["CRQ-001"].status, FindingStatus::Live);
+
+    scan_cache.record_removal(FindingKind::CrqLink, "CRQ-001", occurrence("ddd", "b.md", 1));
+    let finding = &scan_cache.crq_links["CRQ-001"];
+    assert_eq!(finding.status, FindingStatus::Removed);
+    assert_eq!(finding.removed_in_commit.as_deref(), Some("ddd"));
+    assert_eq!(scan_cache.removed_links().len(), 1);
+
+    scan_cache.record(FindingKind::CrqLink, "CRQ-001", occurrence("eee", "c.md", 1));
+    assert_eq!(scan_cache.crq_links["CRQ-001"].status, FindingStatus::Live);
+    assert!(scan_cache.removed_links().is_empty());
+}
+
+#[test]
+fn test_full_path_includes_repository_and_submodule() {
+    let mut occurrence = occurrence("aaa", "src/lib.rs", 1);
+    occurrence.submodule = Some("libs/sub".to_string());
+    assert_eq!(occurrence.full_path(), "libs/sub/src/lib.rs");
+    occurrence.repository = Some("tool".to_string());
+    assert_eq!(occurrence.full_path(), "tool:libs/sub/src/lib.rs");
+}
+
+#[test]
+fn test_blame_is_replaced_per_repository() {
+    let mut scan_cache = ScanCache::new();
+    scan_cache.record(FindingKind::Url, "https://a.org", occurrence("aaa", "a.md", 1));
+    assert_eq!(scan_cache.live_paths(None).into_iter().collect::<Vec<_>>(), vec!["a.md"]);
+
+    scan_cache.record_blame(FindingKind::Url, "https://a.org", occurrence("aaa", "a.md", 1));
+    scan_cache.record_blame(FindingKind::Url, "https://unseen.org", occurrence("aaa", "a.md", 2));
+    assert_eq!(scan_cache.urls["https://a.org"].blame.len(), 1);
+    assert!(!scan_cache.urls.contains_key("https://unseen.org"));
+
+    scan_cache.clear_blame(None);
+    assert!(scan_cache.urls["https://a.org"].blame.is_empty());
+}
+
+#[test]
+fn test_edited_line_keeps_per_file_count() {
+    let mut scan_cache = ScanCache::new();
+    scan_cache.record(FindingKind::Url, "https://a.org/", occurrence("aaa", "b.md", 1));
+    // Editing the line around the URL adds it again and deletes the old line
+    scan_cache.record(FindingKind::Url, "https://a.org/", occurrence("bbb", "b.md", 1));
+    scan_cache.record_removal(FindingKind::Url, "https://a.org/", occurrence("bbb", "b.md", 1));
+
+    let finding = &scan_cache.urls["https://a.org/"];
+    assert_eq!(finding.counts_by_file.get("b.md"), Some(&1));
+    assert_eq!(finding.counts_by_commit.len(), 2);
+
+    // Renamed in a later commit, then deleted under its new name
+    scan_cache.record_rename("b.md", "c.md");
+    scan_cache.record_removal(FindingKind::Url, "https://a.org/", occurrence("ccc", "c.md", 1));
+    assert!(scan_cache.urls["https://a.org/"].counts_by_file.is_empty());
+}
+
+#[test]
+fn test_removal_follows_chained_renames() {
+    let mut scan_cache = ScanCache::new();
+    scan_cache.record(FindingKind::Url, "https://a.org/", occurrence("aaa", "a.md", 1));
+    scan_cache.record(FindingKind::Url, "https://a.org/", occurrence("aaa", "a.md", 2));
+    scan_cache.record_rename("a.md", "b.md");
+    // Renamed again and edited in the same commit: the deleted line names the parent-side path
+    scan_cache.record_rename("b.md", "c.md");
+    scan_cache.record_removal(FindingKind::Url, "https://a.org/", occurrence("ccc", "b.md", 1));
+    assert_eq!(scan_cache.renamed_paths.get("a.md").map(String::as_str), Some("c.md"));
+    assert_eq!(scan_cache.urls["https://a.org/"].counts_by_file.get("c.md"), Some(&1));
+
+    // Renaming back reuses the old name, which then counts for itself again
+    scan_cache.record_rename("c.md", "a.md");
+    scan_cache.record_removal(FindingKind::Url, "https://a.org/", occurrence("ddd", "a.md", 2));
+    let finding = &scan_cache.urls["https://a.org/"];
+    assert!(finding.counts_by_file.is_empty());
+    assert_eq!(finding.status, FindingStatus::Removed);
+}
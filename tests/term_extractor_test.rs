@@ -0,0 +1,21 @@
+use wikidata_tool::term_extractor::{split_identifier, TermExtractor};
+
+#[test]
+fn test_split_identifier() {
+    assert_eq!(split_identifier("fetch_and_cache_wikidata_entity"), vec!["fetch", "and", "cache", "wikidata", "entity"]);
+    assert_eq!(split_identifier("WikipediaHttpClient"), vec!["wikipedia", "http", "client"]);
+    assert_eq!(split_identifier("parseHTTPResponse2xx"), vec!["parse", "http", "response", "2", "xx"]);
+}
+
+#[test]
+fn test_extract_drops_keywords_and_stopwords() {
+    let term_extractor = TermExtractor::new(false);
+    let terms = term_extractor.extract("pub fn fetch_and_cache_wikidata_entity(client: &Client) -> Result<Option<WikidataEntity>>");
+    assert_eq!(terms, vec!["fetch", "cache", "wikidata", "entity", "client", "client", "wikidata", "entity"]);
+}
+
+#[test]
+fn test_extract_with_stemming() {
+    let term_extractor = TermExtractor::new(true);
+    assert_eq!(term_extractor.extract("parsed entities processing"), vec!["pars", "entity", "process"]);
+}
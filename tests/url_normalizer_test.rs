@@ -0,0 +1,35 @@
+use wikidata_tool::url_normalizer::{canonicalize_url, classify_url, UrlClass};
+
+#[test]
+fn test_canonicalize_strips_trailing_punctuation_and_unbalanced_parens() {
+    assert_eq!(canonicalize_url("https://en.wikipedia.org/wiki/Shebang_(Unix)),").as_deref(), Some("https://en.wikipedia.org/wiki/Shebang_(Unix)"));
+    assert_eq!(canonicalize_url("https://techcrunch.com\",").as_deref(), Some("https://techcrunch.com/"));
+    assert_eq!(canonicalize_url("https://example.org/a\":").as_deref(), Some("https://example.org/a"));
+}
+
+#[test]
+fn test_canonicalize_wikimedia_hosts_and_titles() {
+    assert_eq!(canonicalize_url("http://en.m.wikipedia.org/wiki/Rust_%28programming_language%29").as_deref(), Some("https://en.wikipedia.org/wiki/Rust_(programming_language)"));
+    assert_eq!(canonicalize_url("https://de.wikipedia.org/w/index.php?title=Z%C3%BCrich&action=edit").as_deref(), Some("https://de.wikipedia.org/wiki/Zürich"));
+    assert_eq!(canonicalize_url("https://m.wikidata.org/wiki/Special:EntityData/Q768046.json").as_deref(), Some("https://www.wikidata.org/wiki/Q768046"));
+    assert_eq!(canonicalize_url("https://www.wikidata.org/wiki/Property:P31").as_deref(), Some("https://www.wikidata.org/wiki/Property:P31"));
+}
+
+#[test]
+fn test_classify_url() {
+    assert_eq!(classify_url("https://en.wikipedia.org/wiki/Rust_(programming_language)"), UrlClass::WikipediaArticle { language: "en".to_string(), title: "Rust_(programming_language)".to_string() });
+    assert_eq!(classify_url("https://www.wikidata.org/wiki/Q768046"), UrlClass::WikidataEntity { id: "Q768046".to_string() });
+    assert_eq!(classify_url("https://github.com/meta-introspector/rust-tool-prelude.git"), UrlClass::GitHubRepo { owner: "meta-introspector".to_string(), repo: "rust-tool-prelude".to_string() });
+    assert_eq!(classify_url("https://techcrunch.com/"), UrlClass::Other);
+}
+
+#[test]
+fn test_canonical_titles_survive_text_matching() {
+    assert_eq!(canonicalize_url("https://en.wikipedia.org/wiki/Hilbert%27s_problems").as_deref(), Some("https://en.wikipedia.org/wiki/Hilbert's_problems"));
+    assert_eq!(canonicalize_url("https://en.wikipedia.org/wiki/A%22B%3CC%3ED%60E%7CF%5CG%5EH").as_deref(), Some("https://en.wikipedia.org/wiki/A%22B%3CC%3ED%60E%7CF%5CG%5EH"));
+    // A final character that trailing-punctuation trimming would strip stays encoded
+    assert_eq!(canonicalize_url("https://en.wikipedia.org/wiki/Washington,_D.C%2E").as_deref(), Some("https://en.wikipedia.org/wiki/Washington,_D.C%2E"));
+    for canonical in ["https://en.wikipedia.org/wiki/Hilbert's_problems", "https://en.wikipedia.org/wiki/Washington,_D.C%2E"] {
+        assert_eq!(canonicalize_url(canonical).as_deref(), Some(canonical));
+    }
+}
@@ -0,0 +1,39 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use wikidata_tool::wikidata_client::search_wikidata_entities;
+
+/// Serves `body` as JSON to a single request on a local port and returns the endpoint URL.
+fn mock_endpoint(body: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 4096];
+        let length = stream.read(&mut request).unwrap();
+        let request_line = String::from_utf8_lossy(&request[..length]).lines().next().unwrap_or_default().to_string();
+        assert!(request_line.contains("action=wbsearchentities"), "unexpected request: {}", request_line);
+        assert!(request_line.contains("search=rust"), "unexpected request: {}", request_line);
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(), body,
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+    format!("http://{}/w/api.php", address)
+}
+
+#[tokio::test]
+async fn test_search_ranks_label_matches_first() {
+    let api_url = mock_endpoint(r#"{"search": [
+        {"id": "Q2", "label": "Rusty", "match": {"type": "alias", "text": "rust"}},
+        {"id": "Q1", "label": "Rust", "description": "programming language", "match": {"type": "label", "text": "Rust"}},
+        {"id": "Q3", "label": "Iron oxide", "description": "rust", "match": {"type": "description", "text": "rust"}}
+    ]}"#);
+    let client = reqwest::Client::new();
+
+    let candidates = search_wikidata_entities(&client, &api_url, "rust", "en", 5).await.unwrap();
+    let ids: Vec<&str> = candidates.iter().map(|c| c.id.as_str()).collect();
+    assert_eq!(ids, vec!["Q1", "Q2", "Q3"]);
+    assert_eq!(candidates[0].description.as_deref(), Some("programming language"));
+    assert_eq!(candidates[1].match_text, "rust");
+}
@@ -0,0 +1,128 @@
+use wikidata_tool::data_structures::LinkKind;
+use wikidata_tool::wikipedia_parser::{extract_article_data, extract_article_data_with, CleaningOptions};
+
+const URL: &str = "https://en.wikipedia.org/wiki/Rust_(programming_language)";
+
+const ARTICLE: &str = r##"<html><body>
+<div id="mw-navigation"><a href="/wiki/Main_Page">Main page</a><a href="/wiki/Special:Random">Random article</a></div>
+<h1 id="firstHeading">Rust (programming language)</h1>
+<div id="mw-content-text"><div class="mw-parser-output">
+<div role="note" class="hatnote navigation-not-searchable">For the fungus, see <a href="/wiki/Rust_(fungus)">Rust (fungus)</a>.</div>
+<table class="infobox vevent">
+<tr><th colspan="2" class="infobox-above">Rust</th></tr>
+<tr><th class="infobox-label">Paradigms</th><td class="infobox-data">Concurrent, functional<sup class="reference"><a href="#cite_note-1">[1]</a></sup></td></tr>
+<tr><th class="infobox-label">Designed by</th><td class="infobox-data">Graydon Hoare</td></tr>
+</table>
+<p><b>Rust</b> is a general-purpose programming language.<sup class="reference"><a href="#cite_note-1">[1]</a></sup></p>
+<p>It emphasizes performance.</p>
+<div id="toc" class="toc"><ul><li>History</li></ul></div>
+<div class="mw-heading mw-heading2"><h2 id="History">History</h2><span class="mw-editsection">[<a href="/w/index.php?action=edit">edit</a>]</span></div>
+<p>Rust began as a personal project at <a href="/wiki/Mozilla#History">Mozilla</a>; see <a href="/wiki/Template:Rust">Template:Rust</a>, <a href="https://fr.wikipedia.org/wiki/Rust_(langage)">fr</a>, <a href="/wiki/wikt:rust">rust</a>, <a href="/wiki/File:Rust_logo.svg">logo</a> and <a href="https://www.rust-lang.org/">the website</a>.</p>
+<div class="mw-heading mw-heading3"><h3 id="Origins">Origins</h3></div>
+<p>In 2006, Hoare started the project.</p>
+<h2><span class="mw-headline" id="References">References</span></h2>
+<div class="reflist"><ol class="references">
+<li id="cite_note-1"><span class="mw-cite-backlink"><a href="#cite_ref-1">^</a></span> <span class="reference-text">"Rust Reference". Retrieved 2024.</span></li>
+</ol></div>
+<div role="navigation" class="navbox"><a href="/wiki/C%2B%2B">C++</a></div>
+</div></div>
+<div id="catlinks"><div id="mw-normal-catlinks"><ul><li><a href="/wiki/Category:Programming_languages">Programming languages</a></li></ul></div>
+<div id="mw-hidden-catlinks"><ul><li><a href="/wiki/Category:Articles_with_short_description">Articles with short description</a></li></ul></div></div>
+</body></html>"##;
+
+#[test]
+fn test_extract_structured_article() {
+    let article = extract_article_data(ARTICLE, URL).unwrap();
+
+    assert_eq!(article.lead, "Rust is a general-purpose programming language.\n\nIt emphasizes performance.");
+    assert_eq!(article.hatnotes, vec!["For the fungus, see Rust (fungus)."]);
+    let infobox: Vec<(&str, &str)> = article.infobox.iter().map(|f| (f.key.as_str(), f.value.as_str())).collect();
+    assert_eq!(infobox, vec![("Paradigms", "Concurrent, functional"), ("Designed by", "Graydon Hoare")]);
+    assert_eq!(article.categories, vec!["Programming languages"]);
+    assert_eq!(article.references, vec![r#""Rust Reference". Retrieved 2024."#]);
+}
+
+#[test]
+fn test_extract_section_tree() {
+    let article = extract_article_data(ARTICLE, URL).unwrap();
+
+    let headings: Vec<(&str, &str, u8)> = article.sections.iter().map(|s| (s.heading.as_str(), s.anchor.as_str(), s.level)).collect();
+    assert_eq!(headings, vec![("History", "History", 2), ("References", "References", 2)]);
+    let history = &article.sections[0];
+    assert!(history.text.starts_with("Rust began as a personal project at Mozilla;"));
+    assert_eq!(history.subsections.len(), 1);
+    assert_eq!(history.subsections[0].heading, "Origins");
+    assert_eq!(history.subsections[0].text, "In 2006, Hoare started the project.");
+    assert_eq!(article.sections[1].text, "");
+}
+
+#[test]
+fn test_classify_links() {
+    let article = extract_article_data_with(ARTICLE, URL, &keep_everything()).unwrap();
+    let link = |text: &str| article.links.iter().find(|l| l.text == text).unwrap_or_else(|| panic!("no link {}", text));
+
+    let mozilla = link("Mozilla");
+    assert_eq!((mozilla.kind, mozilla.title.as_deref(), mozilla.fragment.as_deref()), (LinkKind::Article, Some("Mozilla"), Some("History")));
+    assert!(mozilla.in_body);
+    assert_eq!(link("Rust (fungus)").title.as_deref(), Some("Rust (fungus)"));
+    assert_eq!(link("Template:Rust").kind, LinkKind::Template);
+    assert_eq!(link("logo").kind, LinkKind::File);
+    assert_eq!(link("fr").kind, LinkKind::Interwiki);
+    assert_eq!(link("fr").title.as_deref(), Some("Rust (langage)"));
+    assert_eq!(link("rust").kind, LinkKind::Interwiki);
+    assert_eq!(link("the website").kind, LinkKind::External);
+    assert_eq!(link("edit").kind, LinkKind::Edit);
+    assert_eq!(link("Programming languages").kind, LinkKind::Category);
+    assert_eq!(link("Programming languages").title.as_deref(), Some("Category:Programming languages"));
+    assert_eq!(link("Random article").kind, LinkKind::Other);
+    let footnote = link("[1]");
+    assert_eq!((footnote.kind, footnote.fragment.as_deref()), (LinkKind::Anchor, Some("cite_note-1")));
+
+    assert!(!link("Main page").in_body);
+    assert!(!link("C++").in_body);
+    assert_eq!(link("C++").title.as_deref(), Some("C++"));
+}
+
+fn keep_everything() -> CleaningOptions {
+    CleaningOptions {
+        navboxes: false,
+        references: false,
+        edit_links: false,
+        hatnotes: false,
+        table_of_contents: false,
+        page_chrome: false,
+        selectors: Vec::new(),
+    }
+}
+
+#[test]
+fn test_default_cleaning_keeps_body_only() {
+    let article = extract_article_data(ARTICLE, URL).unwrap();
+    let texts: Vec<&str> = article.links.iter().map(|l| l.text.as_str()).collect();
+
+    for chrome in ["Main page", "Random article", "C++", "edit", "[1]", "^", "Programming languages"] {
+        assert!(!texts.contains(&chrome), "{} should have been removed", chrome);
+    }
+    assert!(texts.contains(&"Mozilla"));
+    assert!(texts.contains(&"Rust (fungus)"));
+    assert!(article.links.iter().all(|l| l.in_body));
+    assert!(!article.content.contains("[1]"));
+    assert!(!article.content.contains("edit"));
+    assert!(!article.content.contains("Retrieved 2024"));
+    assert!(article.content.contains("For the fungus"));
+    // The structured fields are read before cleaning
+    assert_eq!(article.references.len(), 1);
+    assert_eq!(article.categories, vec!["Programming languages"]);
+}
+
+#[test]
+fn test_configurable_cleaning() {
+    let options = CleaningOptions { hatnotes: true, selectors: vec!["table.infobox".to_string()], ..CleaningOptions::default() };
+    let article = extract_article_data_with(ARTICLE, URL, &options).unwrap();
+
+    assert!(!article.content.contains("For the fungus"));
+    assert!(!article.content.contains("Graydon Hoare"));
+    assert!(!article.links.iter().any(|l| l.text == "Rust (fungus)"));
+    assert_eq!(article.hatnotes, vec!["For the fungus, see Rust (fungus)."]);
+    assert_eq!(article.infobox.len(), 2);
+}